@@ -1,19 +1,37 @@
 use gtk4::prelude::*;
-use gtk4::{Application, ApplicationWindow, CssProvider, STYLE_PROVIDER_PRIORITY_APPLICATION};
+use gtk4::{Application, ApplicationWindow};
 
 mod parser;
 mod platform;
 mod renderer;
-
-use parser::html_parser::{parse_html, DomNode, WidgetDefinition};
+mod trust;
+
+use parser::config::PROJECT_DEFAULTS_FILE;
+use parser::diagnostics::Diagnostics;
+use parser::directives::evaluate_widget;
+use parser::geometry::{window_geometry, Rect};
+use parser::html_parser::{parse_defaults, parse_html, ParseOptions, WidgetDefinition, WindowConfig};
+use parser::interchange::{export_widgets, import_widgets, widget_file_stem, DataFormat};
+use parser::serializer::serialize_widgets;
+use parser::variables::DataSource;
+use renderer::access::{grant_access, Access};
+use renderer::actions::check_event_handlers;
+use renderer::commands::on_command_output;
 use renderer::gtk_renderer::render_dom_to_gtk;
-
+use renderer::inputs::{restore_inputs, save_inputs};
+use renderer::layout::check_layout;
+use renderer::stylesheet::{load_default_stylesheet, load_global_stylesheet, ScopedStyle};
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 mod utils;
 use utils::VERBOSE;
 
-fn build_ui(app: &Application) {
+fn build_ui(app: &Application, variables: &HashMap<String, String>) {
     use std::fs;
 
     let project_root = std::env::current_dir().expect("No se pudo obtener el directorio actual del proyecto");
@@ -29,6 +47,7 @@ fn build_ui(app: &Application) {
         return;
     }
 
+    let defaults = load_project_defaults(&widget_dir);
     let mut all_widgets = Vec::new();
 
 
@@ -39,33 +58,9 @@ fn build_ui(app: &Application) {
         if let Ok(entry) = entry {
             let path = entry.path();
 
-            if path.extension().and_then(|s| s.to_str()) == Some("ytml") {
+            if is_widget_file(&path) {
                 vprintln!("Cargando: {:?}", path.file_name().unwrap());
-
-                match fs::read_to_string(&path) {
-                    Ok(ytml_content) => {
-                        if let Some(mut widgets) = parse_html(&ytml_content) {
-                            // Si el widget tiene id="main", usar el nombre del archivo como ID
-                            for widget in &mut widgets {
-                                if widget.id == "main" {
-                                    let filename = path
-                                        .file_stem()
-                                        .and_then(|s| s.to_str())
-                                        .unwrap_or("unnamed");
-                                    widget.id = filename.to_string();
-                                }
-                            }
-
-                            all_widgets.extend(widgets);
-                            vprintln!("  ✓ Parseado correctamente");
-                        } else {
-                            eprintln!("  ✗ Error al parsear YTML");
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("  ✗ Error al leer archivo: {}", e);
-                    }
-                }
+                all_widgets.extend(load_widget_file(&path, &defaults, variables, false));
             }
         }
     }
@@ -78,7 +73,196 @@ fn build_ui(app: &Application) {
     vprintln!("\nTotal de widgets encontrados: {}", all_widgets.len());
 
     for widget_def in all_widgets {
+        open_widget(app, widget_def, &defaults, variables);
+    }
+}
+
+// Los widgets llegan cargados sin sus permisos (ver `ParseOptions::trusted`).
+// Uno que pide permisos nuevos o distintos de los aprobados no se abre hasta
+// que el usuario contesta: si los aprueba se vuelve a cargar con ellos y si
+// los rechaza se abre tal cual, sin ninguno
+fn open_widget(
+    app: &Application,
+    widget_def: WidgetDefinition,
+    defaults: &WindowConfig,
+    variables: &HashMap<String, String>,
+) {
+    if widget_def.permissions.is_empty() {
         create_widget_window(app, &widget_def);
+        return;
+    }
+    if trust::is_trusted(&widget_def) {
+        create_widget_window(app, &reload_trusted(&widget_def, defaults, variables));
+        return;
+    }
+
+    let app_ref = app.clone();
+    let defaults = defaults.clone();
+    let variables = variables.clone();
+    let pending = widget_def.clone();
+    trust::ask_trust(app, &widget_def, move |approved| {
+        if approved {
+            trust::trust(&pending);
+            create_widget_window(&app_ref, &reload_trusted(&pending, &defaults, &variables));
+        } else {
+            eprintln!("  ⚠ Permisos rechazados, el widget '{}' se abre sin ninguno", pending.id);
+            create_widget_window(&app_ref, &pending);
+        }
+    });
+}
+
+// Vuelve a cargar el archivo del widget con sus permisos concedidos, para leer
+// los <data src> e <include> que los necesitan. Si el archivo ya no pide los
+// permisos que se aprobaron, el widget se queda sin ninguno
+fn reload_trusted(
+    widget_def: &WidgetDefinition,
+    defaults: &WindowConfig,
+    variables: &HashMap<String, String>,
+) -> WidgetDefinition {
+    let reloaded = widget_def
+        .data
+        .file
+        .as_deref()
+        .map(|path| load_widget_file(path, defaults, variables, true))
+        .unwrap_or_default()
+        .into_iter()
+        .find(|reloaded| reloaded.id == widget_def.id);
+
+    match reloaded {
+        Some(reloaded) if reloaded.permissions == widget_def.permissions => reloaded,
+        _ => {
+            eprintln!("  ⚠ El widget '{}' cambió al aprobar sus permisos, se abre sin ninguno", widget_def.id);
+            widget_def.clone()
+        }
+    }
+}
+
+thread_local! {
+    /// Avisos de carga ya mostrados: un archivo con permisos se carga dos veces.
+    static SHOWN_WARNINGS: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+fn warn_once(warning: &impl std::fmt::Display) {
+    let warning = warning.to_string();
+    if SHOWN_WARNINGS.with(|shown| shown.borrow_mut().insert(warning.clone())) {
+        eprintln!("  ⚠ {}", warning);
+    }
+}
+
+// Parsea un archivo de widgets mostrando los avisos; si falla devuelve una
+// lista vacía. Sin `trusted` no se lee nada que necesite los permisos
+fn load_widget_file(
+    path: &Path,
+    defaults: &WindowConfig,
+    variables: &HashMap<String, String>,
+    trusted: bool,
+) -> Vec<WidgetDefinition> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("  ✗ Error al leer archivo: {}", e);
+            return Vec::new();
+        }
+    };
+
+    let options = ParseOptions {
+        file: Some(path.to_path_buf()),
+        defaults: defaults.clone(),
+        variables: variables.clone(),
+        trusted,
+    };
+
+    let result = match DataFormat::from_path(path) {
+        Some(format) => import_widgets(&content, format, &options),
+        None => parse_html(&content, &options),
+    };
+
+    match result {
+        Ok(output) => {
+            for warning in &output.warnings {
+                warn_once(warning);
+            }
+
+            let mut widgets = output.widgets;
+
+            // Los valores de diseño y las acciones inválidos se avisan aquí y
+            // no en cada render
+            let mut diagnostics = Diagnostics::new(&content, Some(path));
+            for widget in &widgets {
+                check_layout(&widget.template, &mut diagnostics);
+                check_event_handlers(&widget.template, &mut diagnostics);
+            }
+            for warning in diagnostics.into_warnings() {
+                warn_once(&warning);
+            }
+
+            // Si el widget tiene id="main", usar el nombre del archivo como ID
+            for widget in &mut widgets {
+                if widget.id == "main" {
+                    let filename = widget_file_stem(path).unwrap_or("unnamed");
+                    widget.id = filename.to_string();
+                }
+            }
+
+            vprintln!("  ✓ Parseado correctamente");
+            widgets
+        }
+        Err(err) => {
+            for warning in &err.warnings {
+                warn_once(warning);
+            }
+            eprintln!("  ✗ Error al parsear YTML: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+// Los fragmentos (`_nombre.ytml` o en subcarpetas) y los valores por defecto
+// del proyecto no son widgets independientes
+fn is_widget_file(path: &Path) -> bool {
+    let file_name = path.file_name().and_then(|s| s.to_str()).unwrap_or("");
+
+    (path.extension().and_then(|s| s.to_str()) == Some("ytml") || DataFormat::from_path(path).is_some())
+        && file_name != PROJECT_DEFAULTS_FILE
+        && !file_name.starts_with('_')
+}
+
+fn load_project_defaults(widget_dir: &Path) -> WindowConfig {
+    let path = widget_dir.join(PROJECT_DEFAULTS_FILE);
+
+    if !path.exists() {
+        return WindowConfig::default();
+    }
+
+    vprintln!("Cargando valores por defecto: {:?}", path);
+
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("  ✗ Error al leer {}: {}", PROJECT_DEFAULTS_FILE, e);
+            return WindowConfig::default();
+        }
+    };
+
+    let options = ParseOptions {
+        file: Some(path),
+        ..Default::default()
+    };
+
+    match parse_defaults(&source, &options) {
+        Ok((defaults, warnings)) => {
+            for warning in &warnings {
+                eprintln!("  ⚠ {}", warning);
+            }
+            defaults
+        }
+        Err(err) => {
+            for warning in &err.warnings {
+                eprintln!("  ⚠ {}", warning);
+            }
+            eprintln!("  ✗ Error al parsear {}: {}", PROJECT_DEFAULTS_FILE, err);
+            WindowConfig::default()
+        }
     }
 }
 
@@ -88,67 +272,212 @@ fn create_widget_window(app: &Application, widget_def: &WidgetDefinition) {
     vprintln!("\n=== CREANDO WIDGET: {} ===", widget_def.id);
     vprintln!("  - Tamaño: {}x{}", config.width, config.height);
     vprintln!("  - Decoraciones: {}", config.decorations);
-    vprintln!("  - Posición: {:?}", (config.x, config.y));
-
-    vprintln!("\n=== ESTRUCTURA DOM ===");
-    print_dom_tree(&widget_def.body, 0);
-    vprintln!("======================\n");
+    vprintln!("  - Posición: {:?} (anchor {}, margin {})", (config.x, config.y), config.anchor, config.margin);
 
     let window = ApplicationWindow::builder()
         .application(app)
-        .default_width(config.width as i32)
-        .default_height(config.height as i32)
         .decorated(config.decorations)
         .resizable(config.resizable)
         .build();
 
-    let css_provider = CssProvider::new();
-    css_provider.load_from_path("style.css");
+    let access = Access {
+        widget: widget_def.id.clone(),
+        permissions: widget_def.data.permissions.clone(),
+        file: widget_def.data.file.clone(),
+    };
+    grant_access(&window, access.clone());
 
-    gtk4::style_context_add_provider_for_display(
-        &gtk4::gdk::Display::default().expect("Could not connect to a display."),
-        &css_provider,
-        STYLE_PROVIDER_PRIORITY_APPLICATION,
-    );
+    // Los <style> y <link> del widget solo afectan a esta ventana
+    let style = ScopedStyle::attach(&window, &access);
+    let body = style.load(&widget_def.body);
 
-    let root_widget = render_dom_to_gtk(&widget_def.body);
+    let root_widget = render_dom_to_gtk(&body);
     window.set_child(Some(&root_widget));
 
+    // Archivos de datos y órdenes con salida cambian los datos de la misma copia
+    let shared = Rc::new(RefCell::new(widget_def.clone()));
+    watch_data_files(&window, &shared, &style);
+    watch_command_outputs(&window, &shared, &style);
+    resize_to_workarea(&window, config);
+
     vprintln!("✓ Ventana GTK creada para widget '{}'", widget_def.id);
     vprintln!("✓ Widget root renderizado: {:?}", root_widget.widget_name());
     window.present();
     vprintln!("✓ Ventana mostrada");
 
-    let win_width = config.width;
-    let win_height = config.height;
-    let x_pos = config.x;
-    let y_pos = config.y;
-
     #[cfg(target_os = "windows")]
-    platform::set_as_desktop_widget(
-        &window,
-        win_width as i32,
-        win_height as i32,
-        x_pos.map(|v| v as i32),
-        y_pos.map(|v| v as i32),
-    );
+    platform::set_as_desktop_widget(&window, config);
+
+    #[cfg(not(target_os = "windows"))]
+    if config.x.is_some() || config.y.is_some() || config.anchor != Default::default() || config.margin != Default::default() {
+        eprintln!(
+            "  ⚠ El widget '{}' pide una posición (x, y, anchor o margin), pero en esta plataforma aún no se aplica: solo el tamaño",
+            widget_def.id
+        );
+    }
+
+    watch_monitors(&window, config);
+}
+
+// Área del monitor de la ventana (o del primero si aún no se ha mostrado).
+// GTK no da el área de trabajo en todas las plataformas, así que aquí se usa
+// la geometría completa; el backend de Windows recalcula con la suya.
+fn monitor_workarea(window: &ApplicationWindow) -> Rect {
+    let display = WidgetExt::display(window);
+    let monitor = window
+        .surface()
+        .and_then(|surface| display.monitor_at_surface(&surface))
+        .or_else(|| {
+            display
+                .monitors()
+                .item(0)
+                .and_then(|item| item.downcast::<gtk4::gdk::Monitor>().ok())
+        });
+
+    match monitor {
+        Some(monitor) => {
+            let geometry = monitor.geometry();
+            Rect {
+                x: geometry.x(),
+                y: geometry.y(),
+                width: geometry.width(),
+                height: geometry.height(),
+            }
+        }
+        None => Rect {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        },
+    }
 }
 
-fn print_dom_tree(node: &DomNode, level: usize) {
-    let indent = "  ".repeat(level);
-    vprintln!("{}Tag: {}", indent, node.tag_name);
+// Solo el tamaño: GTK 4 no deja mover ventanas, la posición la pone el
+// backend de Windows (`platform::set_as_desktop_widget`)
+fn resize_to_workarea(window: &ApplicationWindow, config: &WindowConfig) {
+    let geometry = window_geometry(config, monitor_workarea(window));
+    window.set_default_size(geometry.width, geometry.height);
+}
 
-    if !node.attributes.is_empty() {
-        vprintln!("{}  Attrs: {:?}", indent, node.attributes);
+// Recalcula tamaño y posición cuando se conecta un monitor o cambia su resolución
+fn watch_monitors(window: &ApplicationWindow, config: &WindowConfig) {
+    let relayout: Rc<dyn Fn()> = {
+        let window = window.downgrade();
+        let config = config.clone();
+        Rc::new(move || {
+            let Some(window) = window.upgrade() else {
+                return;
+            };
+            vprintln!("Cambió la geometría del monitor, recolocando la ventana");
+            resize_to_workarea(&window, &config);
+
+            #[cfg(target_os = "windows")]
+            platform::set_as_desktop_widget(&window, &config);
+        })
+    };
+
+    let monitors = WidgetExt::display(window).monitors();
+    let mut handlers = Vec::new();
+
+    for item in monitors.iter::<gtk4::gdk::Monitor>().flatten() {
+        let relayout = relayout.clone();
+        let handler = item.connect_geometry_notify(move |_| relayout());
+        handlers.push((item.upcast::<gtk4::glib::Object>(), handler));
     }
 
-    if let Some(text) = &node.text_content {
-        vprintln!("{}  Text: '{}'", indent, text);
+    let handler = monitors.connect_items_changed(move |_, _, _, _| relayout());
+    handlers.push((monitors.upcast(), handler));
+
+    // Los monitores sobreviven a la ventana, así que hay que desconectarse
+    let handlers = RefCell::new(handlers);
+    window.connect_destroy(move |_| {
+        for (object, handler) in handlers.take() {
+            object.disconnect(handler);
+        }
+    });
+}
+
+// Re-evalúa y vuelve a renderizar el widget con sus datos actuales
+fn rerender(window: &ApplicationWindow, widget_def: &WidgetDefinition, style: &ScopedStyle) {
+    let (body, warnings) = evaluate_widget(widget_def);
+    for warning in &warnings {
+        eprintln!("  ⚠ {}", warning);
     }
+    let body = style.load(&body);
+
+    // Lo que el usuario haya escrito sobrevive al nuevo render
+    let inputs = save_inputs(window.upcast_ref());
+    window.set_child(Some(&render_dom_to_gtk(&body)));
+    restore_inputs(window.upcast_ref(), &inputs);
+}
+
+// Re-renderiza el widget cuando cambia uno de sus archivos <data src>
+fn watch_data_files(window: &ApplicationWindow, widget_def: &Rc<RefCell<WidgetDefinition>>, style: &ScopedStyle) {
+    use gtk4::gio;
+
+    let mut monitors = Vec::new();
+
+    for path in widget_def.borrow().data.data_files() {
+        let file = gio::File::for_path(&path);
+
+        match file.monitor_file(gio::FileMonitorFlags::NONE, gio::Cancellable::NONE) {
+            Ok(monitor) => {
+                let window = window.downgrade();
+                let widget_def = widget_def.clone();
+                let style = style.clone();
 
-    for child in &node.children {
-        print_dom_tree(child, level + 1);
+                monitor.connect_changed(move |_, _, _, event| {
+                    if event != gio::FileMonitorEvent::ChangesDoneHint {
+                        return;
+                    }
+                    let Some(window) = window.upgrade() else {
+                        return;
+                    };
+
+                    let widget_def = widget_def.borrow();
+                    vprintln!("Datos modificados, re-evaluando widget '{}'", widget_def.id);
+                    rerender(&window, &widget_def, &style);
+                });
+
+                monitors.push(monitor);
+            }
+            Err(e) => eprintln!("  ✗ No se puede vigilar {:?}: {}", path, e),
+        }
     }
+
+    if !monitors.is_empty() {
+        // Los monitores se mantienen vivos mientras exista la ventana
+        window.connect_destroy(move |_| {
+            for monitor in &monitors {
+                monitor.cancel();
+            }
+        });
+    }
+}
+
+// La salida de `exec(output=nombre)` pasa a ser `<data name="nombre">` y el
+// widget se vuelve a renderizar para mostrarla
+fn watch_command_outputs(window: &ApplicationWindow, widget_def: &Rc<RefCell<WidgetDefinition>>, style: &ScopedStyle) {
+    let window_ref = window.downgrade();
+    let widget_def = widget_def.clone();
+    let style = style.clone();
+
+    on_command_output(window, move |name, output| {
+        let Some(window) = window_ref.upgrade() else {
+            return;
+        };
+
+        widget_def
+            .borrow_mut()
+            .data
+            .sources
+            .insert(name.to_string(), DataSource::Inline(output.to_json()));
+
+        let widget_def = widget_def.borrow();
+        vprintln!("Salida de orden en '{}', re-evaluando widget '{}'", name, widget_def.id);
+        rerender(&window, &widget_def, &style);
+    });
 }
 
 fn main() {
@@ -159,10 +488,70 @@ fn main() {
         args.remove(pos);
     }
 
+    // --var nombre=valor (repetible) para las variables {{ nombre }} de los widgets
+    let mut variables = HashMap::new();
+    while let Some(pos) = args.iter().position(|arg| arg == "--var") {
+        args.remove(pos);
+        if pos >= args.len() {
+            eprintln!("✗ --var necesita un valor nombre=valor");
+            break;
+        }
+
+        let assignment = args.remove(pos);
+        match assignment.split_once('=') {
+            Some((name, value)) => {
+                variables.insert(name.trim().to_string(), value.to_string());
+            }
+            None => eprintln!("✗ --var ignorado, se esperaba nombre=valor: {}", assignment),
+        }
+    }
+
+    // Modos que procesan un archivo y terminan sin abrir ventanas: --format
+    // imprime YTML canónico y --dump-json/--dump-toml el árbol parseado
+    for flag in ["--format", "--dump-json", "--dump-toml"] {
+        let Some(pos) = args.iter().position(|arg| arg == flag) else {
+            continue;
+        };
+        let Some(file) = args.get(pos + 1) else {
+            eprintln!("✗ {} necesita la ruta de un archivo de widgets", flag);
+            std::process::exit(2);
+        };
+
+        let path = Path::new(file);
+        let defaults = load_project_defaults(path.parent().unwrap_or(Path::new("")));
+        // El archivo lo pasa el usuario a mano: sus permisos cuentan como aprobados
+        let widgets = load_widget_file(path, &defaults, &variables, true);
+        if widgets.is_empty() {
+            std::process::exit(1);
+        }
+
+        let output = match flag {
+            "--format" => Ok(serialize_widgets(&widgets)),
+            "--dump-json" => export_widgets(&widgets, DataFormat::Json),
+            _ => export_widgets(&widgets, DataFormat::Toml),
+        };
+
+        match output {
+            Ok(text) => println!("{}", text.trim_end()),
+            Err(e) => {
+                eprintln!("✗ Error al exportar {}: {}", file, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let app = Application::builder()
         .application_id("htmlwidgets.rust.gtk")
         .build();
 
-    app.connect_activate(build_ui);
+    // Las hojas de estilos globales se cargan una sola vez para todas las ventanas
+    app.connect_startup(|_| {
+        load_default_stylesheet();
+        if let Ok(project_root) = std::env::current_dir() {
+            load_global_stylesheet(&project_root.join("style.css"));
+        }
+    });
+    app.connect_activate(move |app| build_ui(app, &variables));
     app.run_with_args(&args);
 }
@@ -0,0 +1,145 @@
+use kuchiki::{Attributes, NodeRef};
+use std::str::FromStr;
+
+use super::diagnostics::{Diagnostics, Span};
+use super::html_parser::WindowConfig;
+use super::source::SourceMap;
+
+/// Archivo opcional en `widgets/` con los valores por defecto de todo el proyecto.
+pub const PROJECT_DEFAULTS_FILE: &str = "defaults.ytml";
+
+const CONFIG_KEYS: &[(&str, &[&str])] = &[
+    ("config", &[]),
+    ("defaults", &[]),
+    ("window", &["width", "height", "x", "y", "anchor", "margin"]),
+    ("decorations", &["enabled"]),
+    ("transparent", &["enabled"]),
+    ("resizable", &["enabled"]),
+];
+
+/// Lee un bloque `<config>` o `<defaults>` partiendo de `base`; solo se
+/// sobrescriben los valores que aparecen en el bloque.
+pub(crate) fn read_config(
+    config_node: &NodeRef,
+    base: &WindowConfig,
+    source_map: &SourceMap,
+    diagnostics: &mut Diagnostics,
+) -> WindowConfig {
+    let mut config = base.clone();
+
+    check_config_keys(config_node, source_map, diagnostics);
+
+    if let Ok(window_node) = config_node.select_first("window") {
+        let span = source_map.span_of(window_node.as_node());
+        let attrs = window_node.attributes.borrow();
+
+        if let Some(width) = parse_attr(&attrs, "width", span, diagnostics) {
+            config.width = width;
+        }
+        if let Some(height) = parse_attr(&attrs, "height", span, diagnostics) {
+            config.height = height;
+        }
+        if let Some(x) = parse_attr(&attrs, "x", span, diagnostics) {
+            config.x = Some(x);
+        }
+        if let Some(y) = parse_attr(&attrs, "y", span, diagnostics) {
+            config.y = Some(y);
+        }
+        if let Some(anchor) = parse_attr(&attrs, "anchor", span, diagnostics) {
+            config.anchor = anchor;
+        }
+        if let Some(margin) = parse_attr(&attrs, "margin", span, diagnostics) {
+            config.margin = margin;
+        }
+    }
+
+    if let Some(enabled) = read_enabled(config_node, "decorations", source_map, diagnostics) {
+        config.decorations = enabled;
+    }
+    if let Some(enabled) = read_enabled(config_node, "transparent", source_map, diagnostics) {
+        config.transparent = enabled;
+    }
+    if let Some(enabled) = read_enabled(config_node, "resizable", source_map, diagnostics) {
+        config.resizable = enabled;
+    }
+
+    config
+}
+
+// <decorations>, <transparent> y <resizable> solo tienen el atributo `enabled`
+fn read_enabled(
+    config_node: &NodeRef,
+    tag: &str,
+    source_map: &SourceMap,
+    diagnostics: &mut Diagnostics,
+) -> Option<bool> {
+    let node = config_node.select_first(tag).ok()?;
+    let span = source_map.span_of(node.as_node());
+    let attrs = node.attributes.borrow();
+
+    parse_bool_attr(&attrs, "enabled", span, diagnostics)
+}
+
+fn parse_attr<T: FromStr>(
+    attrs: &Attributes,
+    name: &str,
+    span: Option<Span>,
+    diagnostics: &mut Diagnostics,
+) -> Option<T> {
+    let raw = attrs.get(name)?;
+
+    match raw.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            diagnostics.warning(
+                span,
+                format!("valor inválido para '{}': \"{}\", se usa el valor por defecto", name, raw),
+            );
+            None
+        }
+    }
+}
+
+fn parse_bool_attr(
+    attrs: &Attributes,
+    name: &str,
+    span: Option<Span>,
+    diagnostics: &mut Diagnostics,
+) -> Option<bool> {
+    match attrs.get(name)? {
+        "true" => Some(true),
+        "false" => Some(false),
+        raw => {
+            diagnostics.warning(
+                span,
+                format!("'{}' debe ser \"true\" o \"false\", no \"{}\"", name, raw),
+            );
+            None
+        }
+    }
+}
+
+// Avisar de etiquetas o atributos de configuración que no se reconocen
+fn check_config_keys(config_node: &NodeRef, source_map: &SourceMap, diagnostics: &mut Diagnostics) {
+    for node in config_node.inclusive_descendants() {
+        let Some(element) = node.as_element() else {
+            continue;
+        };
+        let span = source_map.span_of(&node);
+        let tag = element.name.local.to_string();
+
+        match CONFIG_KEYS.iter().find(|(key, _)| *key == tag) {
+            Some((_, known_attrs)) => {
+                for (name, _) in element.attributes.borrow().map.iter() {
+                    if !known_attrs.contains(&&*name.local) {
+                        diagnostics.warning(
+                            span,
+                            format!("atributo desconocido '{}' en <{}>", name.local, tag),
+                        );
+                    }
+                }
+            }
+            None => diagnostics.warning(span, format!("clave de configuración desconocida <{}>", tag)),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Posición (1-based) de un nodo dentro del archivo fuente.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub span: Option<Span>,
+    pub snippet: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file = self
+            .file
+            .as_deref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "<ytml>".to_string());
+
+        match self.span {
+            Some(span) => write!(f, "{}:{}:{}: ", file, span.line, span.column)?,
+            None => write!(f, "{}: ", file)?,
+        }
+
+        let level = match self.severity {
+            Severity::Warning => "aviso",
+            Severity::Error => "error",
+        };
+        write!(f, "{}: {}", level, self.message)?;
+
+        if let (Some(snippet), Some(span)) = (&self.snippet, self.span) {
+            write!(f, "\n    | {}", snippet)?;
+            write!(f, "\n    | {}^", " ".repeat(span.column.saturating_sub(1)))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error fatal de parseo junto con los avisos reunidos antes de fallar.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub error: Diagnostic,
+    pub warnings: Vec<Diagnostic>,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Acumula los avisos de un archivo y construye los errores con su snippet.
+pub(crate) struct Diagnostics<'a> {
+    file: Option<PathBuf>,
+    source: &'a str,
+    warnings: Vec<Diagnostic>,
+}
+
+impl<'a> Diagnostics<'a> {
+    pub fn new(source: &'a str, file: Option<&Path>) -> Self {
+        Diagnostics {
+            file: file.map(Path::to_path_buf),
+            source,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn warning(&mut self, span: Option<Span>, message: impl Into<String>) {
+        let diagnostic = self.diagnostic(Severity::Warning, span, message.into());
+        self.warnings.push(diagnostic);
+    }
+
+    pub fn error(&mut self, span: Option<Span>, message: impl Into<String>) -> ParseError {
+        ParseError {
+            error: self.diagnostic(Severity::Error, span, message.into()),
+            warnings: std::mem::take(&mut self.warnings),
+        }
+    }
+
+    /// Añade los avisos de otro archivo (p. ej. un fragmento incluido).
+    pub fn extend(&mut self, warnings: Vec<Diagnostic>) {
+        self.warnings.extend(warnings);
+    }
+
+    /// Propaga un error de otro archivo anteponiendo los avisos propios.
+    pub fn propagate(&mut self, mut err: ParseError) -> ParseError {
+        let mut warnings = std::mem::take(&mut self.warnings);
+        warnings.append(&mut err.warnings);
+        err.warnings = warnings;
+        err
+    }
+
+    pub fn into_warnings(self) -> Vec<Diagnostic> {
+        self.warnings
+    }
+
+    fn diagnostic(&self, severity: Severity, span: Option<Span>, message: String) -> Diagnostic {
+        let snippet = span
+            .and_then(|s| self.source.lines().nth(s.line - 1))
+            .map(|line| line.trim_end().to_string());
+
+        Diagnostic {
+            severity,
+            message,
+            file: self.file.clone(),
+            span,
+            snippet,
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::diagnostics::{Diagnostic, Diagnostics};
+use super::expr::{eval_expr, is_truthy, parse_expr, value_to_string};
+use super::html_parser::{tidy_text, DomNode, WidgetDefinition};
+use super::variables::{interpolate_str, DataSource, WidgetData};
+
+/// Atributos con acciones (`onclick`, `onchange`, `onsubmit`).
+pub const EVENT_HANDLERS: &[&str] = &["onclick", "onchange", "onsubmit"];
+
+/// Atributo del nodo evaluado con los valores de los `{{ }}` de un manejador,
+/// en una lista JSON. En las acciones cada `{{ }}` pasa a ser `{%n}` y el
+/// renderer lo sustituye por el valor `n` después de separar las acciones y
+/// sus argumentos: pegado al texto, un `;` o un espacio del valor añadiría
+/// acciones o argumentos.
+pub fn handler_values_attribute(handler: &str) -> String {
+    format!("ytml-{}-values", handler)
+}
+
+/// Vuelve a evaluar la plantilla del widget con los datos actuales (p. ej.
+/// después de que cambie uno de sus archivos de datos).
+pub fn evaluate_widget(widget: &WidgetDefinition) -> (DomNode, Vec<Diagnostic>) {
+    let mut diagnostics = Diagnostics::new("", widget.data.file.as_deref());
+    let body = evaluate(&widget.template, &widget.data, &mut diagnostics);
+    (body, diagnostics.into_warnings())
+}
+
+/// Aplica `for-each` e `if` y resuelve los `{{ }}` sobre una copia de la plantilla.
+///
+/// `for-each` se aplica antes que `if`, así que la condición puede usar la
+/// variable del bucle.
+pub(crate) fn evaluate(template: &DomNode, data: &WidgetData, diagnostics: &mut Diagnostics) -> DomNode {
+    let mut scope = Scope {
+        values: load_sources(data, diagnostics),
+        locals: Vec::new(),
+    };
+
+    for (name, value) in &data.variables {
+        scope.values.entry(name.clone()).or_insert_with(|| Value::String(value.clone()));
+    }
+    for (name, value) in &data.overrides {
+        scope.values.insert(name.clone(), Value::String(value.clone()));
+    }
+
+    let mut body = DomNode {
+        children: Vec::new(),
+        ..template.clone()
+    };
+    for child in &template.children {
+        body.children.extend(evaluate_node(child, &mut scope, diagnostics));
+    }
+
+    // Los `if` y los `{{ }}` pueden dejar textos contiguos, vacíos o con espacios
+    // de más; se normalizan igual que al parsear
+    tidy_tree(&mut body, false);
+    body
+}
+
+fn tidy_tree(node: &mut DomNode, preformatted: bool) {
+    let preformatted = preformatted || node.tag_name == "pre";
+    for child in &mut node.children {
+        tidy_tree(child, preformatted);
+    }
+    tidy_text(node, preformatted);
+}
+
+struct Scope {
+    values: HashMap<String, Value>,
+    /// Variables de los `for-each` en curso, la más interna al final.
+    locals: Vec<(String, Value)>,
+}
+
+impl Scope {
+    // Rutas con puntos: `item.name`, `servers.0.host`
+    fn lookup(&self, path: &str) -> Option<Value> {
+        let mut segments = path.split('.');
+        let root = segments.next()?;
+
+        // Las variables del bucle tapan a las del widget con el mismo nombre
+        let local = self
+            .locals
+            .iter()
+            .rev()
+            .find(|(name, _)| name == root)
+            .map(|(_, value)| value.clone());
+
+        let mut value = match local {
+            Some(value) => value,
+            // Una <var> puede tener puntos en el nombre. El entorno solo se
+            // lee con `<var env="...">`, nunca por un nombre desconocido
+            None => match self.values.get(path) {
+                Some(value) => return Some(value.clone()),
+                None => self.values.get(root).cloned()?,
+            },
+        };
+
+        for segment in segments {
+            value = match value {
+                Value::Object(mut map) => map.remove(segment).unwrap_or(Value::Null),
+                Value::Array(mut items) => match segment.parse::<usize>() {
+                    Ok(index) if index < items.len() => items.swap_remove(index),
+                    _ => Value::Null,
+                },
+                _ => Value::Null,
+            };
+        }
+
+        Some(value)
+    }
+}
+
+fn load_sources(data: &WidgetData, diagnostics: &mut Diagnostics) -> HashMap<String, Value> {
+    let mut values = HashMap::new();
+
+    for (name, source) in &data.sources {
+        let value = match source {
+            DataSource::Inline(value) => value.clone(),
+            DataSource::File(path) => {
+                match data.check_read(path) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        values.insert(name.clone(), Value::Null);
+                        continue;
+                    }
+                    Err(denied) => {
+                        diagnostics.warning(None, format!("permiso denegado para los datos '{}': {}", name, denied));
+                        values.insert(name.clone(), Value::Null);
+                        continue;
+                    }
+                }
+
+                let parsed = std::fs::read_to_string(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()));
+
+                match parsed {
+                    Ok(value) => value,
+                    Err(e) => {
+                        diagnostics.warning(
+                            None,
+                            format!("no se pudieron cargar los datos '{}' de {}: {}", name, path.display(), e),
+                        );
+                        Value::Null
+                    }
+                }
+            }
+        };
+        values.insert(name.clone(), value);
+    }
+
+    values
+}
+
+fn evaluate_node(node: &DomNode, scope: &mut Scope, diagnostics: &mut Diagnostics) -> Vec<DomNode> {
+    let Some(for_each) = node.attributes.get("for-each") else {
+        return evaluate_single(node, scope, diagnostics).into_iter().collect();
+    };
+
+    let Some((item_name, index_name, list_path)) = parse_for_each(for_each) else {
+        diagnostics.warning(
+            node.span,
+            format!("for-each inválido \"{}\", se esperaba \"item in lista\"", for_each),
+        );
+        return Vec::new();
+    };
+
+    let items = match scope.lookup(list_path) {
+        Some(Value::Array(items)) => items,
+        // Las variables de texto pueden contener una lista en JSON
+        Some(Value::String(text)) => match serde_json::from_str(&text) {
+            Ok(Value::Array(items)) => items,
+            _ => {
+                diagnostics.warning(node.span, format!("'{}' no es una lista", list_path));
+                return Vec::new();
+            }
+        },
+        Some(Value::Null) => Vec::new(),
+        Some(_) => {
+            diagnostics.warning(node.span, format!("'{}' no es una lista", list_path));
+            return Vec::new();
+        }
+        None => {
+            diagnostics.warning(node.span, format!("variable desconocida '{}'", list_path));
+            return Vec::new();
+        }
+    };
+
+    let mut repeated = node.clone();
+    repeated.attributes.remove("for-each");
+
+    let mut nodes = Vec::with_capacity(items.len());
+    for (index, item) in items.into_iter().enumerate() {
+        scope.locals.push((item_name.to_string(), item));
+        if let Some(index_name) = index_name {
+            scope.locals.push((index_name.to_string(), Value::from(index)));
+        }
+
+        nodes.extend(evaluate_single(&repeated, scope, diagnostics));
+
+        if index_name.is_some() {
+            scope.locals.pop();
+        }
+        scope.locals.pop();
+    }
+
+    nodes
+}
+
+// "item in lista" o "item, i in lista"
+fn parse_for_each(input: &str) -> Option<(&str, Option<&str>, &str)> {
+    let (bindings, list) = input.split_once(" in ")?;
+    let list = list.trim();
+
+    let (item, index) = match bindings.split_once(',') {
+        Some((item, index)) => (item.trim(), Some(index.trim())),
+        None => (bindings.trim(), None),
+    };
+
+    if item.is_empty() || list.is_empty() || index.is_some_and(str::is_empty) {
+        return None;
+    }
+
+    Some((item, index, list))
+}
+
+fn evaluate_single(node: &DomNode, scope: &mut Scope, diagnostics: &mut Diagnostics) -> Option<DomNode> {
+    if let Some(condition) = node.attributes.get("if") {
+        let expr = match parse_expr(condition) {
+            Ok(expr) => expr,
+            Err(e) => {
+                diagnostics.warning(node.span, format!("expresión if=\"{}\" inválida: {}", condition, e));
+                return None;
+            }
+        };
+
+        let mut unknown = Vec::new();
+        let value = eval_expr(&expr, &|path| scope.lookup(path), &mut unknown);
+        for name in unknown {
+            diagnostics.warning(node.span, format!("variable desconocida '{}' en if", name));
+        }
+
+        if !is_truthy(&value) {
+            return None;
+        }
+    }
+
+    let lookup = |name: &str| scope.lookup(name).map(|value| value_to_string(&value));
+
+    let text_content = node
+        .text_content
+        .as_ref()
+        .map(|text| interpolate_str(text, node.span, &lookup, diagnostics));
+
+    let mut attributes = HashMap::with_capacity(node.attributes.len());
+    for (name, value) in node.attributes.iter().filter(|(name, _)| name.as_str() != "if") {
+        let value = if !value.contains("{{") {
+            value.clone()
+        } else if EVENT_HANDLERS.contains(&name.as_str()) {
+            let values = RefCell::new(Vec::new());
+            let placeholder = |name: &str| {
+                let mut values = values.borrow_mut();
+                values.push(lookup(name)?);
+                Some(format!("{{%{}}}", values.len() - 1))
+            };
+            let handler = interpolate_str(value, node.span, &placeholder, diagnostics);
+            attributes.insert(handler_values_attribute(name), Value::from(values.into_inner()).to_string());
+            handler
+        } else {
+            interpolate_str(value, node.span, &lookup, diagnostics)
+        };
+        attributes.insert(name.clone(), value);
+    }
+
+    let mut children = Vec::with_capacity(node.children.len());
+    for child in &node.children {
+        children.extend(evaluate_node(child, scope, diagnostics));
+    }
+
+    Some(DomNode {
+        tag_name: node.tag_name.clone(),
+        attributes,
+        children,
+        text_content,
+        span: node.span,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::handler_values_attribute;
+    use crate::parser::html_parser::{parse_html, DomNode, ParseOptions};
+
+    fn texts(node: &DomNode) -> Vec<String> {
+        match &node.text_content {
+            Some(text) => vec![text.clone()],
+            None => node.children.iter().flat_map(texts).collect(),
+        }
+    }
+
+    fn evaluated(source: &str, options: &ParseOptions) -> Vec<String> {
+        let output = parse_html(source, options).unwrap();
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+        texts(&output.widgets[0].body)
+    }
+
+    #[test]
+    fn loop_variables_shadow_widget_values() {
+        let source = r#"
+            <widget id="w">
+                <var name="s" value="global"/>
+                <var name="s.name" value="global"/>
+                <data name="servers">[{"name": "a"}, {"name": "b"}]</data>
+                <body><p for-each="s in servers">{{ s.name }}</p><p>{{ s }}</p></body>
+            </widget>
+        "#;
+        assert_eq!(evaluated(source, &ParseOptions::default()), ["a", "b", "global"]);
+    }
+
+    #[test]
+    fn command_line_variables_override_declarations() {
+        let source = r#"
+            <widget id="w">
+                <var name="city" value="Madrid"/>
+                <data name="servers">[{"name": "a"}]</data>
+                <body><p>{{ city }}</p><p>{{ servers }}</p></body>
+            </widget>
+        "#;
+        let options = ParseOptions {
+            variables: [
+                ("city".to_string(), "Lisboa".to_string()),
+                ("servers".to_string(), "ninguno".to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        assert_eq!(evaluated(source, &options), ["Lisboa", "ninguno"]);
+    }
+
+    #[test]
+    fn declarations_apply_without_command_line_variables() {
+        let source = r#"
+            <widget id="w">
+                <var name="city" value="Madrid"/>
+                <data name="count">3</data>
+                <body><p>{{ city }}</p><p>{{ count }}</p></body>
+            </widget>
+        "#;
+        assert_eq!(evaluated(source, &ParseOptions::default()), ["Madrid", "3"]);
+    }
+
+    #[test]
+    fn environment_variables_need_a_declaration() {
+        let source = r#"
+            <widget id="w">
+                <var name="path" env="PATH"/>
+                <var name="missing" env="YTML_TEST_UNSET_VARIABLE" value="none"/>
+                <body><p>{{ path }}</p><p>{{ missing }}</p><p>{{ PATH }}</p></body>
+            </widget>
+        "#;
+        let output = parse_html(source, &ParseOptions::default()).unwrap();
+
+        let path = std::env::var("PATH").unwrap_or_default();
+        assert_eq!(texts(&output.widgets[0].body), [path.as_str(), "none"]);
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.warnings[0].message, "variable desconocida 'PATH'");
+    }
+
+    #[test]
+    fn handler_values_do_not_change_the_actions() {
+        let source = r#"
+            <widget id="w">
+                <var name="title" value="a; quit"/>
+                <data name="files">["mis notas.txt"]</data>
+                <body>
+                    <button for-each="f in files" onclick="exec:cat {{ f }}; print:{{ title }}">Abrir</button>
+                    <p id="{{ title }}">x</p>
+                </body>
+            </widget>
+        "#;
+        let output = parse_html(source, &ParseOptions::default()).unwrap();
+        let body = &output.widgets[0].body;
+        let button = body.children.iter().find(|child| child.tag_name == "button").unwrap();
+
+        assert_eq!(button.attributes["onclick"], "exec:cat {%0}; print:{%1}");
+        assert_eq!(button.attributes[&handler_values_attribute("onclick")], r#"["mis notas.txt","a; quit"]"#);
+        // Fuera de los manejadores el valor se pega tal cual
+        assert!(body.children.iter().any(|child| child.attributes.get("id").map(String::as_str) == Some("a; quit")));
+    }
+
+    #[test]
+    fn declared_data_is_read_only_once_approved() {
+        let source = r#"
+            <widget id="w">
+                <permissions><read path="/nonexistent-ytml/notes"/></permissions>
+                <data name="notes" src="/nonexistent-ytml/notes/today.json"/>
+                <body><p>{{ notes.title }}</p></body>
+            </widget>
+        "#;
+        let options = |trusted| ParseOptions {
+            file: Some("/nonexistent-ytml/widgets/w.ytml".into()),
+            trusted,
+            ..Default::default()
+        };
+
+        // Sin aprobar no se intenta leer; aprobados, sí (y el archivo no existe)
+        assert!(parse_html(source, &options(false)).unwrap().warnings.is_empty());
+        let warnings = parse_html(source, &options(true)).unwrap().warnings;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.starts_with("no se pudieron cargar los datos 'notes'"));
+    }
+}
@@ -0,0 +1,287 @@
+use serde_json::Value;
+use std::cmp::Ordering;
+
+/// Expresión de la directiva `if`.
+///
+/// Admite rutas (`item.name`, `servers.0.host`), literales (`"texto"`, `42`,
+/// `true`, `false`, `null`), comparaciones (`==`, `!=`, `<`, `<=`, `>`, `>=`),
+/// `!`, `&&`, `||` y paréntesis.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expr {
+    Literal(Value),
+    Path(String),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(CompareOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Op(&'static str),
+    LParen,
+    RParen,
+}
+
+pub(crate) fn parse_expr(input: &str) -> Result<Expr, String> {
+    let tokens = tokenize(input)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.or()?;
+
+    match parser.tokens.get(parser.pos) {
+        None => Ok(expr),
+        Some(token) => Err(format!("símbolo inesperado {:?}", token)),
+    }
+}
+
+/// Evalúa la expresión; `lookup` resuelve las rutas y devuelve `None` si la
+/// variable raíz no existe (se anota en `unknown`).
+pub(crate) fn eval_expr(
+    expr: &Expr,
+    lookup: &dyn Fn(&str) -> Option<Value>,
+    unknown: &mut Vec<String>,
+) -> Value {
+    match expr {
+        Expr::Literal(value) => value.clone(),
+        Expr::Path(path) => lookup(path).unwrap_or_else(|| {
+            unknown.push(path.clone());
+            Value::Null
+        }),
+        Expr::Not(inner) => Value::Bool(!is_truthy(&eval_expr(inner, lookup, unknown))),
+        Expr::And(left, right) => {
+            let left = eval_expr(left, lookup, unknown);
+            if is_truthy(&left) {
+                eval_expr(right, lookup, unknown)
+            } else {
+                left
+            }
+        }
+        Expr::Or(left, right) => {
+            let left = eval_expr(left, lookup, unknown);
+            if is_truthy(&left) {
+                left
+            } else {
+                eval_expr(right, lookup, unknown)
+            }
+        }
+        Expr::Compare(op, left, right) => {
+            let left = eval_expr(left, lookup, unknown);
+            let right = eval_expr(right, lookup, unknown);
+            Value::Bool(compare(*op, &left, &right))
+        }
+    }
+}
+
+/// Valores falsos: `null`, `false`, `0`, `""`, `"false"`, `[]` y `{}`.
+pub(crate) fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty() && s != "false",
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+    }
+}
+
+/// Texto de un valor tal y como se interpola en el YTML.
+pub(crate) fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+// Las variables de texto que contienen números se comparan como números
+fn compare(op: CompareOp, left: &Value, right: &Value) -> bool {
+    let ordering = match (as_number(left), as_number(right)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b),
+        _ => Some(value_to_string(left).cmp(&value_to_string(right))),
+    };
+
+    match (op, ordering) {
+        (CompareOp::Eq, ordering) => ordering == Some(Ordering::Equal),
+        (CompareOp::Ne, ordering) => ordering != Some(Ordering::Equal),
+        (_, None) => false,
+        (CompareOp::Lt, Some(o)) => o == Ordering::Less,
+        (CompareOp::Le, Some(o)) => o != Ordering::Greater,
+        (CompareOp::Gt, Some(o)) => o == Ordering::Greater,
+        (CompareOp::Ge, Some(o)) => o != Ordering::Less,
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '(' || c == ')' {
+            tokens.push(if c == '(' { Token::LParen } else { Token::RParen });
+            i += 1;
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            let end = chars[i + 1..]
+                .iter()
+                .position(|&ch| ch == c)
+                .ok_or("cadena sin cerrar")?;
+            tokens.push(Token::Str(chars[i + 1..i + 1 + end].iter().collect()));
+            i += end + 2;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit())) {
+            let start = i;
+            i += 1;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let number: String = chars[start..i].iter().collect();
+            let number = number
+                .parse()
+                .map_err(|_| format!("número inválido '{}'", number))?;
+            tokens.push(Token::Number(number));
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '-' | '.'))
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let op = ["==", "!=", "<=", ">=", "&&", "||"]
+            .into_iter()
+            .find(|op| *op == two)
+            .or_else(|| ["<", ">", "!"].into_iter().find(|op| op.starts_with(c)))
+            .ok_or_else(|| format!("carácter inesperado '{}'", c))?;
+        i += op.len();
+        tokens.push(Token::Op(op));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(self.tokens.get(self.pos), Some(Token::Op(current)) if *current == op) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut left = self.and()?;
+        while self.eat_op("||") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut left = self.not()?;
+        while self.eat_op("&&") {
+            left = Expr::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat_op("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op("==")) => CompareOp::Eq,
+            Some(Token::Op("!=")) => CompareOp::Ne,
+            Some(Token::Op("<")) => CompareOp::Lt,
+            Some(Token::Op("<=")) => CompareOp::Le,
+            Some(Token::Op(">")) => CompareOp::Gt,
+            Some(Token::Op(">=")) => CompareOp::Ge,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+
+        let right = self.primary()?;
+        Ok(Expr::Compare(op, Box::new(left), Box::new(right)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or("expresión incompleta")?;
+        self.pos += 1;
+
+        match token {
+            Token::LParen => {
+                let inner = self.or()?;
+                if self.tokens.get(self.pos) != Some(&Token::RParen) {
+                    return Err("falta ')'".to_string());
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::Str(s) => Ok(Expr::Literal(Value::String(s))),
+            Token::Number(n) => Ok(Expr::Literal(
+                serde_json::Number::from_f64(n).map(Value::Number).unwrap_or(Value::Null),
+            )),
+            Token::Ident(ident) => Ok(match ident.as_str() {
+                "true" => Expr::Literal(Value::Bool(true)),
+                "false" => Expr::Literal(Value::Bool(false)),
+                "null" => Expr::Literal(Value::Null),
+                _ => Expr::Path(ident),
+            }),
+            other => Err(format!("símbolo inesperado {:?}", other)),
+        }
+    }
+}
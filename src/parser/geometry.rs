@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use super::html_parser::WindowConfig;
+
+/// Tamaño de `<window>`: píxeles (`300`) o porcentaje del área de trabajo (`25%`).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "ConfigValue", into = "ConfigValue")]
+pub enum Length {
+    Px(f32),
+    Percent(f32),
+}
+
+impl Length {
+    pub fn resolve(self, available: i32) -> i32 {
+        match self {
+            Length::Px(px) => px.round() as i32,
+            Length::Percent(percent) => (available as f32 * percent / 100.0).round() as i32,
+        }
+    }
+}
+
+impl FromStr for Length {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, percent) = match s.strip_suffix('%') {
+            Some(number) => (number, true),
+            None => (s.strip_suffix("px").unwrap_or(s), false),
+        };
+
+        let value: f32 = number
+            .trim()
+            .parse()
+            .map_err(|_| format!("longitud inválida \"{}\"", s))?;
+        if value < 0.0 {
+            return Err(format!("la longitud no puede ser negativa: \"{}\"", s));
+        }
+
+        Ok(if percent { Length::Percent(value) } else { Length::Px(value) })
+    }
+}
+
+impl fmt::Display for Length {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Length::Px(px) => write!(f, "{}", px),
+            Length::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+/// Punto del área de trabajo al que se pega la ventana.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+const ANCHOR_NAMES: &[(&str, Anchor)] = &[
+    ("top-left", Anchor::TopLeft),
+    ("top", Anchor::Top),
+    ("top-right", Anchor::TopRight),
+    ("left", Anchor::Left),
+    ("center", Anchor::Center),
+    ("right", Anchor::Right),
+    ("bottom-left", Anchor::BottomLeft),
+    ("bottom", Anchor::Bottom),
+    ("bottom-right", Anchor::BottomRight),
+];
+
+#[derive(Clone, Copy)]
+enum Align {
+    Start,
+    Center,
+    End,
+}
+
+impl Anchor {
+    fn horizontal(self) -> Align {
+        match self {
+            Anchor::TopLeft | Anchor::Left | Anchor::BottomLeft => Align::Start,
+            Anchor::Top | Anchor::Center | Anchor::Bottom => Align::Center,
+            Anchor::TopRight | Anchor::Right | Anchor::BottomRight => Align::End,
+        }
+    }
+
+    fn vertical(self) -> Align {
+        match self {
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => Align::Start,
+            Anchor::Left | Anchor::Center | Anchor::Right => Align::Center,
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => Align::End,
+        }
+    }
+}
+
+impl FromStr for Anchor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let name = if s == "centre" { "center" } else { s };
+
+        ANCHOR_NAMES
+            .iter()
+            .find(|(anchor_name, _)| *anchor_name == name)
+            .map(|(_, anchor)| *anchor)
+            .ok_or_else(|| format!("anchor desconocido \"{}\"", s))
+    }
+}
+
+impl fmt::Display for Anchor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = ANCHOR_NAMES
+            .iter()
+            .find(|(_, anchor)| anchor == self)
+            .map(|(name, _)| *name)
+            .unwrap_or("center");
+        f.write_str(name)
+    }
+}
+
+/// Separación respecto a los bordes del área de trabajo, en píxeles. Se
+/// escribe como en CSS: `24`, `24 48`, `8 16 24` o `8 16 24 32`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ConfigValue", into = "ConfigValue")]
+pub struct Margins {
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+    pub left: i32,
+}
+
+impl FromStr for Margins {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split_whitespace()
+            .map(|value| value.strip_suffix("px").unwrap_or(value).parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("margin inválido \"{}\"", s.trim()))?;
+
+        let (top, right, bottom, left) = match values.as_slice() {
+            [all] => (*all, *all, *all, *all),
+            [vertical, horizontal] => (*vertical, *horizontal, *vertical, *horizontal),
+            [top, horizontal, bottom] => (*top, *horizontal, *bottom, *horizontal),
+            [top, right, bottom, left] => (*top, *right, *bottom, *left),
+            _ => return Err(format!("margin necesita de 1 a 4 valores, no \"{}\"", s.trim())),
+        };
+
+        Ok(Margins { top, right, bottom, left })
+    }
+}
+
+impl fmt::Display for Margins {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let Margins { top, right, bottom, left } = *self;
+
+        if top == right && right == bottom && bottom == left {
+            write!(f, "{}", top)
+        } else if top == bottom && right == left {
+            write!(f, "{} {}", top, right)
+        } else if right == left {
+            write!(f, "{} {} {}", top, right, bottom)
+        } else {
+            write!(f, "{} {} {} {}", top, right, bottom, left)
+        }
+    }
+}
+
+// En JSON/TOML se admite tanto un número como el texto del atributo YTML
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ConfigValue {
+    Number(f32),
+    Text(String),
+}
+
+impl TryFrom<ConfigValue> for Length {
+    type Error = String;
+
+    fn try_from(value: ConfigValue) -> Result<Self, Self::Error> {
+        match value {
+            ConfigValue::Number(px) => Ok(Length::Px(px)),
+            ConfigValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Length> for ConfigValue {
+    fn from(length: Length) -> Self {
+        match length {
+            Length::Px(px) => ConfigValue::Number(px),
+            percent => ConfigValue::Text(percent.to_string()),
+        }
+    }
+}
+
+impl TryFrom<ConfigValue> for Margins {
+    type Error = String;
+
+    fn try_from(value: ConfigValue) -> Result<Self, Self::Error> {
+        match value {
+            ConfigValue::Number(px) => {
+                let px = px.round() as i32;
+                Ok(Margins { top: px, right: px, bottom: px, left: px })
+            }
+            ConfigValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<Margins> for ConfigValue {
+    fn from(margins: Margins) -> Self {
+        ConfigValue::Text(margins.to_string())
+    }
+}
+
+/// Rectángulo en píxeles de pantalla.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Posición y tamaño de la ventana dentro del área de trabajo del monitor.
+///
+/// Los porcentajes se calculan sobre el área de trabajo. `x`/`y`, si están,
+/// son relativas a su esquina superior izquierda; en el eje que falte se usa
+/// `anchor` con los márgenes correspondientes.
+pub fn window_geometry(config: &WindowConfig, workarea: Rect) -> Rect {
+    let width = config.width.resolve(workarea.width);
+    let height = config.height.resolve(workarea.height);
+    let margin = config.margin;
+
+    let x = match config.x {
+        Some(x) => workarea.x + x,
+        None => align(config.anchor.horizontal(), workarea.x, workarea.width, width, margin.left, margin.right),
+    };
+    let y = match config.y {
+        Some(y) => workarea.y + y,
+        None => align(config.anchor.vertical(), workarea.y, workarea.height, height, margin.top, margin.bottom),
+    };
+
+    Rect { x, y, width, height }
+}
+
+fn align(align: Align, start: i32, available: i32, size: i32, margin_start: i32, margin_end: i32) -> i32 {
+    match align {
+        Align::Start => start + margin_start,
+        Align::Center => start + (available - size) / 2,
+        Align::End => start + available - size - margin_end,
+    }
+}
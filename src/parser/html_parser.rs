@@ -1,20 +1,62 @@
 use kuchiki::traits::*;
-use std::collections::HashMap;
+use kuchiki::NodeRef;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+use super::config::read_config;
+use super::diagnostics::{Diagnostic, Diagnostics, ParseError, Span};
+use super::geometry::{Anchor, Length, Margins};
+use super::include::expand_includes;
+use super::permissions::{read_permissions, Permissions};
+use super::template::{collect_templates, expand_components, register_template, Templates};
+use super::directives::evaluate;
+use super::source::{internal_tag_name, normalize, original_tag_name, SourceMap};
+use super::variables::{collect_declarations, declare, WidgetData};
+
+/// Etiquetas que fuera de un `<body>` explícito se aplican a todo el archivo.
+const FILE_LEVEL_TAGS: &[&str] = &[
+    "config", "permissions", "defaults", "var", "data", "style", "link", "template",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct DomNode {
     pub tag_name: String,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", serialize_with = "sorted")]
     pub attributes: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<DomNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub text_content: Option<String>,
+    #[serde(skip)]
+    pub span: Option<Span>,
 }
 
-#[derive(Debug, Clone)]
+// Atributos en orden alfabético para que las exportaciones sean estables
+fn sorted<S: Serializer>(attributes: &HashMap<String, String>, serializer: S) -> Result<S::Ok, S::Error> {
+    attributes.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+// Dos nodos son iguales si tienen el mismo contenido, aunque vengan de
+// posiciones distintas del fuente
+impl PartialEq for DomNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.tag_name == other.tag_name
+            && self.attributes == other.attributes
+            && self.children == other.children
+            && self.text_content == other.text_content
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WindowConfig {
-    pub width: f32,
-    pub height: f32,
+    pub width: Length,
+    pub height: Length,
     pub x: Option<i32>,
     pub y: Option<i32>,
+    /// Posición en los ejes sin `x`/`y`.
+    pub anchor: Anchor,
+    pub margin: Margins,
     pub decorations: bool,
     pub transparent: bool,
     pub resizable: bool,
@@ -23,10 +65,12 @@ pub struct WindowConfig {
 impl Default for WindowConfig {
     fn default() -> Self {
         WindowConfig {
-            width: 800.0,
-            height: 600.0,
+            width: Length::Px(800.0),
+            height: Length::Px(600.0),
             x: None,
             y: None,
+            anchor: Anchor::default(),
+            margin: Margins::default(),
             decorations: true,
             transparent: false,
             resizable: true,
@@ -34,171 +78,379 @@ impl Default for WindowConfig {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Al exportar solo se incluyen `id`, `config`, `permissions` y el body; ver
+/// [`export_widgets`](super::interchange::export_widgets).
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WidgetDefinition {
     pub id: String,
     pub config: WindowConfig,
+    /// Body evaluado, listo para renderizar.
     pub body: DomNode,
+    /// Manifiesto `<permissions>` tal y como lo declara el widget.
+    #[serde(default, skip_serializing_if = "Permissions::is_empty")]
+    pub permissions: Permissions,
+    /// Body antes de aplicar `for-each`/`if` y `{{ }}`, para re-evaluarlo.
+    #[serde(skip)]
+    pub template: DomNode,
+    #[serde(skip)]
+    pub data: WidgetData,
+}
+
+/// Opciones de parseo de un archivo YTML.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub file: Option<PathBuf>,
+    /// Configuración que heredan todos los widgets (p. ej. la del proyecto).
+    pub defaults: WindowConfig,
+    /// Variables de línea de comandos; tienen prioridad sobre las `<var>` y `<data>`.
+    pub variables: HashMap<String, String>,
+    /// Los permisos de los widgets ya están aprobados. Si no, solo se lee la
+    /// carpeta de cada widget: lo que necesita sus permisos se queda sin leer
+    /// hasta que el usuario los aprueba y se vuelve a cargar el archivo.
+    pub trusted: bool,
+}
+
+/// Resultado de un parseo correcto junto con los avisos no fatales.
+#[derive(Debug, Clone)]
+pub struct ParseOutput {
+    pub widgets: Vec<WidgetDefinition>,
+    pub warnings: Vec<Diagnostic>,
 }
 
-pub fn parse_html(source: &str) -> Option<Vec<WidgetDefinition>> {
-    let dom = kuchiki::parse_html().from_utf8().one(source.as_bytes());
+pub fn parse_html(source: &str, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    let (dom, source_map) = parse_document(source);
+    let mut diagnostics = Diagnostics::new(source, options.file.as_deref());
     let mut widgets = Vec::new();
 
+    // Archivos en expansión para detectar <include> cíclicos
+    let mut include_stack: Vec<PathBuf> = options
+        .file
+        .iter()
+        .filter_map(|file| file.canonicalize().ok())
+        .collect();
+
+    // Los <defaults> del archivo se aplican sobre los que vienen en las opciones
+    let defaults = match dom.select_first("defaults") {
+        Ok(defaults_node) => read_config(
+            defaults_node.as_node(),
+            &options.defaults,
+            &source_map,
+            &mut diagnostics,
+        ),
+        Err(()) => options.defaults.clone(),
+    };
+
+    // <var> y <data> declarados fuera de cualquier widget
+    let mut file_data = WidgetData {
+        file: options.file.clone(),
+        ..Default::default()
+    };
+    if let Ok(declaration_nodes) = dom.select("var, data") {
+        for declaration_node in declaration_nodes {
+            let node = declaration_node.as_node();
+            if !is_inside(node, "widget") && !is_inside(node, "body") {
+                declare(&build_dom_node(node, &source_map), &mut file_data, &mut diagnostics);
+            }
+        }
+    }
+
+    // Componentes definidos fuera de cualquier <body>, disponibles para todos
+    // los widgets. Sus <include> solo tienen los permisos comunes: el
+    // directorio del archivo
+    let mut templates = Templates::new();
+    if let Ok(template_nodes) = dom.select(internal_tag_name("template")) {
+        for template_node in template_nodes {
+            if !is_inside(template_node.as_node(), "body") {
+                let mut template = build_dom_node(template_node.as_node(), &source_map);
+                expand_includes(
+                    &mut template,
+                    options.file.as_deref(),
+                    &file_data,
+                    &mut diagnostics,
+                    &mut include_stack,
+                )?;
+                register_template(template, &mut templates, &mut diagnostics);
+            }
+        }
+    }
+
+    // <style> y <link> fuera de cualquier widget (html5ever deja en <head> los
+    // que preceden al <body> del formato antiguo): se aplican a todos los widgets
+    let mut file_styles = Vec::new();
+    if let Ok(style_nodes) = dom.select("style, link") {
+        for style_node in style_nodes {
+            let node = style_node.as_node();
+            if !is_inside(node, "widget") && !is_inside(node, "body") && !is_inside(node, "template") {
+                file_styles.push(build_dom_node(node, &source_map));
+            }
+        }
+    }
+
     // Intentar parsear formato nuevo (múltiples widgets)
-    let widget_nodes: Vec<_> = dom.select("widget").unwrap().collect();
-    
+    let widget_nodes: Vec<_> = match dom.select("widget") {
+        Ok(nodes) => nodes.collect(),
+        Err(()) => return Err(diagnostics.error(None, "selector inválido: widget")),
+    };
+
     if !widget_nodes.is_empty() {
         // Formato nuevo: múltiples widgets
         for widget_node in widget_nodes {
-            let widget_element = widget_node.as_node().as_element().unwrap();
-            let widget_id = widget_element.attributes.borrow()
+            let widget_span = source_map.span_of(widget_node.as_node());
+            let widget_id = widget_node.attributes.borrow()
                 .get("id")
                 .unwrap_or("unnamed")
                 .to_string();
 
-            let mut config = WindowConfig::default();
-
             // Parsear config dentro de este widget
-            if let Ok(config_node) = widget_node.as_node().select_first("config") {
-                if let Ok(window_node) = config_node.as_node().select_first("window") {
-                    let element = window_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(width) = attrs.get("width") {
-                        config.width = width.parse().unwrap_or(800.0);
-                    }
-                    if let Some(height) = attrs.get("height") {
-                        config.height = height.parse().unwrap_or(600.0);
-                    }
-                    if let Some(x) = attrs.get("x") {
-                        config.x = x.parse().ok();
-                    }
-                    if let Some(y) = attrs.get("y") {
-                        config.y = y.parse().ok();
-                    }
+            let config = match widget_node.as_node().select_first("config") {
+                Ok(config_node) => {
+                    read_config(config_node.as_node(), &defaults, &source_map, &mut diagnostics)
                 }
+                Err(()) => defaults.clone(),
+            };
 
-                if let Ok(decorations_node) = config_node.as_node().select_first("decorations") {
-                    let element = decorations_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(enabled) = attrs.get("enabled") {
-                        config.decorations = enabled == "true";
-                    }
-                }
+            let permissions = match widget_node.as_node().select_first("permissions") {
+                Ok(permissions_node) => read_permissions(permissions_node.as_node(), &source_map, &mut diagnostics),
+                Err(()) => Permissions::default(),
+            };
 
-                if let Ok(transparent_node) = config_node.as_node().select_first("transparent") {
-                    let element = transparent_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(enabled) = attrs.get("enabled") {
-                        config.transparent = enabled == "true";
+            // <var> y <data> del widget declarados fuera de su body
+            let mut data = file_data.clone();
+            data.grant(&permissions, options.trusted);
+            if let Ok(declaration_nodes) = widget_node.as_node().select("var, data") {
+                for declaration_node in declaration_nodes {
+                    let node = declaration_node.as_node();
+                    if !is_inside(node, "body") {
+                        declare(&build_dom_node(node, &source_map), &mut data, &mut diagnostics);
                     }
                 }
+            }
 
-                if let Ok(resizable_node) = config_node.as_node().select_first("resizable") {
-                    let element = resizable_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(enabled) = attrs.get("enabled") {
-                        config.resizable = enabled == "true";
+            // Estilos del widget declarados fuera de su body
+            let mut styles = file_styles.clone();
+            if let Ok(style_nodes) = widget_node.as_node().select("style, link") {
+                for style_node in style_nodes {
+                    let node = style_node.as_node();
+                    if !is_inside(node, "body") && !is_inside(node, "template") {
+                        styles.push(build_dom_node(node, &source_map));
                     }
                 }
             }
 
             // Parsear body
-            if let Ok(body) = widget_node.as_node().select_first("body") {
-                let body_node = build_dom_node(body.as_node());
+            if let Ok(body) = widget_node.as_node().select_first(internal_tag_name("body")) {
+                let mut template = build_body(
+                    body.as_node(),
+                    &source_map,
+                    &templates,
+                    &mut data,
+                    options,
+                    &mut diagnostics,
+                    &mut include_stack,
+                )?;
+                template.children.splice(0..0, styles);
+                let body_node = evaluate(&template, &data, &mut diagnostics);
                 
                 widgets.push(WidgetDefinition {
                     id: widget_id,
                     config,
                     body: body_node,
+                    permissions,
+                    template,
+                    data,
                 });
+            } else {
+                diagnostics.warning(
+                    widget_span,
+                    format!("el widget '{}' no tiene <body> y se ignora", widget_id),
+                );
             }
         }
     } else {
-        // Formato antiguo: un solo widget con <body> y <config> en la raíz
-        if let Ok(body) = dom.select_first("body") {
-            let mut config = WindowConfig::default();
-            
-            // Parsear configuración
-            if let Ok(config_node) = dom.select_first("config") {
-                if let Ok(window_node) = config_node.as_node().select_first("window") {
-                    let element = window_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(width) = attrs.get("width") {
-                        config.width = width.parse().unwrap_or(800.0);
-                    }
-                    if let Some(height) = attrs.get("height") {
-                        config.height = height.parse().unwrap_or(600.0);
-                    }
-                    if let Some(x) = attrs.get("x") {
-                        config.x = x.parse().ok();
-                    }
-                    if let Some(y) = attrs.get("y") {
-                        config.y = y.parse().ok();
-                    }
-                }
-
-                if let Ok(decorations_node) = config_node.as_node().select_first("decorations") {
-                    let element = decorations_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(enabled) = attrs.get("enabled") {
-                        config.decorations = enabled == "true";
-                    }
-                }
+        // Formato antiguo: un solo widget con <body> y <config> en la raíz. Sin
+        // <body> explícito el contenido queda en el <body> implícito de
+        // html5ever, junto a lo que ya se leyó para todo el archivo
+        let body = match dom.select_first(internal_tag_name("body")) {
+            Ok(body) => Some(build_dom_node(body.as_node(), &source_map)),
+            Err(()) => dom
+                .select_first("body")
+                .ok()
+                .map(|body| {
+                    let mut body = build_dom_node(body.as_node(), &source_map);
+                    remove_file_level(&mut body, false);
+                    body
+                })
+                .filter(|body| !body.children.is_empty()),
+        };
 
-                if let Ok(transparent_node) = config_node.as_node().select_first("transparent") {
-                    let element = transparent_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(enabled) = attrs.get("enabled") {
-                        config.transparent = enabled == "true";
-                    }
+        if let Some(body) = body {
+            // Parsear configuración
+            let config = match dom.select_first("config") {
+                Ok(config_node) => {
+                    read_config(config_node.as_node(), &defaults, &source_map, &mut diagnostics)
                 }
+                Err(()) => defaults,
+            };
 
-                if let Ok(resizable_node) = config_node.as_node().select_first("resizable") {
-                    let element = resizable_node.as_node().as_element().unwrap();
-                    let attrs = element.attributes.borrow();
-                    
-                    if let Some(enabled) = attrs.get("enabled") {
-                        config.resizable = enabled == "true";
-                    }
-                }
-            }
+            let permissions = match dom.select_first("permissions") {
+                Ok(permissions_node) => read_permissions(permissions_node.as_node(), &source_map, &mut diagnostics),
+                Err(()) => Permissions::default(),
+            };
             
-            let body_node = build_dom_node(body.as_node());
+            let mut data = file_data;
+            data.grant(&permissions, options.trusted);
+            let body_node = prepare_body(
+                body,
+                &templates,
+                &mut data,
+                options,
+                &mut diagnostics,
+                &mut include_stack,
+            )?;
             
-            // Filtrar cualquier nodo <config> que haya quedado dentro del body
-            let filtered_body = DomNode {
+            // Filtrar cualquier nodo <config> o <permissions> que haya quedado dentro del body
+            let template = DomNode {
                 tag_name: body_node.tag_name,
                 attributes: body_node.attributes,
-                children: body_node.children.into_iter()
-                    .filter(|child| child.tag_name != "config")
+                children: file_styles.into_iter()
+                    .chain(
+                        body_node
+                            .children
+                            .into_iter()
+                            .filter(|child| child.tag_name != "config" && child.tag_name != "permissions"),
+                    )
                     .collect(),
                 text_content: body_node.text_content,
+                span: body_node.span,
             };
+            let filtered_body = evaluate(&template, &data, &mut diagnostics);
             
             widgets.push(WidgetDefinition {
                 id: "main".to_string(),
                 config,
                 body: filtered_body,
+                permissions,
+                template,
+                data,
             });
         }
     }
 
     if widgets.is_empty() {
-        None
-    } else {
-        Some(widgets)
+        return Err(diagnostics.error(None, "no se encontró ningún widget con <body>"));
     }
+
+    Ok(ParseOutput {
+        widgets,
+        warnings: diagnostics.into_warnings(),
+    })
+}
+
+// Construye el DomNode de un <body> con los <include> y componentes ya expandidos;
+// las declaraciones <var>/<data> que contiene pasan a `data`
+fn build_body(
+    body: &NodeRef,
+    source_map: &SourceMap,
+    file_templates: &Templates,
+    data: &mut WidgetData,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<DomNode, ParseError> {
+    prepare_body(
+        build_dom_node(body, source_map),
+        file_templates,
+        data,
+        options,
+        diagnostics,
+        include_stack,
+    )
+}
+
+/// Expande los `<include>` y componentes de un body ya construido (del YTML o
+/// importado de JSON/TOML) y pasa sus declaraciones `<var>`/`<data>` a `data`.
+pub(crate) fn prepare_body(
+    mut body_node: DomNode,
+    file_templates: &Templates,
+    data: &mut WidgetData,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<DomNode, ParseError> {
+    expand_includes(&mut body_node, options.file.as_deref(), data, diagnostics, include_stack)?;
+
+    // Los <template> del body (o de sus fragmentos) solo valen para este widget
+    let mut templates = file_templates.clone();
+    collect_templates(&mut body_node, &mut templates, diagnostics);
+    expand_components(&mut body_node, &templates, diagnostics, &mut Vec::new())?;
+
+    collect_declarations(&mut body_node, data, diagnostics);
+    data.overrides = options.variables.clone();
+
+    Ok(body_node)
+}
+
+// Quita del <body> implícito lo que se lee para todo el archivo
+fn remove_file_level(node: &mut DomNode, preformatted: bool) {
+    let preformatted = preformatted || node.tag_name == "pre";
+    let before = node.children.len();
+    node.children.retain(|child| !FILE_LEVEL_TAGS.contains(&child.tag_name.as_str()));
+    let removed = node.children.len() != before;
+
+    for child in &mut node.children {
+        remove_file_level(child, preformatted);
+    }
+    if removed {
+        tidy_text(node, preformatted);
+    }
+}
+
+// Indica si el nodo está dentro de un elemento con la etiqueta YTML `tag`
+fn is_inside(node: &NodeRef, tag: &str) -> bool {
+    let internal = internal_tag_name(tag);
+
+    node.ancestors().any(|ancestor| {
+        ancestor
+            .as_element()
+            .is_some_and(|element| &*element.name.local == internal)
+    })
+}
+
+/// Lee un archivo de valores por defecto del proyecto, que solo contiene un
+/// bloque `<defaults>` con las mismas etiquetas que `<config>`.
+pub fn parse_defaults(
+    source: &str,
+    options: &ParseOptions,
+) -> Result<(WindowConfig, Vec<Diagnostic>), ParseError> {
+    let (dom, source_map) = parse_document(source);
+    let mut diagnostics = Diagnostics::new(source, options.file.as_deref());
+
+    let Ok(defaults_node) = dom.select_first("defaults") else {
+        return Err(diagnostics.error(None, "no se encontró el bloque <defaults>"));
+    };
+
+    let config = read_config(
+        defaults_node.as_node(),
+        &options.defaults,
+        &source_map,
+        &mut diagnostics,
+    );
+
+    Ok((config, diagnostics.into_warnings()))
 }
 
-fn build_dom_node(kuchiki_node: &kuchiki::NodeRef) -> DomNode {
+pub(crate) fn parse_document(source: &str) -> (NodeRef, SourceMap) {
+    let dom = kuchiki::parse_html().one(normalize(source));
+    let source_map = SourceMap::build(source, &dom);
+    (dom, source_map)
+}
+
+pub(crate) fn build_dom_node(kuchiki_node: &NodeRef, source_map: &SourceMap) -> DomNode {
+    build_node(kuchiki_node, source_map, false)
+}
+
+// Dentro de `<pre>` el texto se conserva tal cual
+fn build_node(kuchiki_node: &NodeRef, source_map: &SourceMap, preformatted: bool) -> DomNode {
     let mut attributes = HashMap::new();
 
     // Obtener el nombre de la etiqueta
@@ -206,7 +458,7 @@ fn build_dom_node(kuchiki_node: &kuchiki::NodeRef) -> DomNode {
         for (key, value) in element.attributes.borrow().map.iter() {
             attributes.insert(key.local.to_string(), value.value.clone());
         }
-        element.name.local.to_string()
+        original_tag_name(&element.name.local).to_string()
     } else {
         "text".to_string()
     };
@@ -216,28 +468,138 @@ fn build_dom_node(kuchiki_node: &kuchiki::NodeRef) -> DomNode {
         attributes,
         children: Vec::new(),
         text_content: None,
+        span: source_map.span_of(kuchiki_node),
     };
+    let preformatted = preformatted || node.tag_name == "pre";
 
     // Recorrer nodos hijos
     for child in kuchiki_node.children() {
         match child.data() {
             kuchiki::NodeData::Element(_) => {
-                node.children.push(build_dom_node(&child));
+                node.children.push(build_node(&child, source_map, preformatted));
             }
             kuchiki::NodeData::Text(text) => {
-                let content = text.borrow().trim().to_string();
-                if !content.is_empty() {
-                    node.children.push(DomNode {
-                        tag_name: "text".to_string(),
-                        attributes: HashMap::new(),
-                        children: vec![],
-                        text_content: Some(content),
-                    });
-                }
+                node.children.push(DomNode {
+                    tag_name: "text".to_string(),
+                    attributes: HashMap::new(),
+                    children: vec![],
+                    text_content: Some(text.borrow().clone()),
+                    span: source_map.span_of(&child),
+                });
             }
             _ => {}
         }
     }
 
+    tidy_text(&mut node, preformatted);
     node
-}
\ No newline at end of file
+}
+
+/// Normaliza los textos hijos de `node`: une los contiguos, colapsa los
+/// espacios como en HTML y quita la sangría del principio y del final.
+/// Dentro de `<pre>` el texto se conserva, salvo la sangría del cierre
+/// (html5ever ya quita el salto de línea tras `<pre>`).
+pub(crate) fn tidy_text(node: &mut DomNode, preformatted: bool) {
+    let mut children: Vec<DomNode> = Vec::with_capacity(node.children.len());
+
+    for mut child in std::mem::take(&mut node.children) {
+        if child.tag_name == "text" {
+            let text = child.text_content.take().unwrap_or_default();
+            if let Some(previous) = children
+                .last_mut()
+                .filter(|previous| previous.tag_name == "text")
+                .and_then(|previous| previous.text_content.as_mut())
+            {
+                previous.push_str(&text);
+                continue;
+            }
+            child.text_content = Some(text);
+        }
+        children.push(child);
+    }
+
+    // Se conserva un espacio entre elementos en línea (`Hola <b>mundo</b>`)
+    if !preformatted {
+        for text in children.iter_mut().filter_map(|c| c.text_content.as_mut()) {
+            *text = collapse_whitespace(text);
+        }
+    }
+
+    let trim_start = !preformatted;
+    let trim_end = !preformatted || node.tag_name == "pre";
+    if let Some(first) = children
+        .first_mut()
+        .filter(|_| trim_start)
+        .and_then(|c| c.text_content.as_mut())
+    {
+        *first = first.trim_start().to_string();
+    }
+    if let Some(last) = children
+        .last_mut()
+        .filter(|_| trim_end)
+        .and_then(|c| c.text_content.as_mut())
+    {
+        *last = last.trim_end().to_string();
+    }
+    children.retain(|child| child.text_content.as_ref().map_or(true, |text| !text.is_empty()));
+
+    node.children = children;
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut last_was_space = false;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !last_was_space {
+                collapsed.push(' ');
+            }
+            last_was_space = true;
+        } else {
+            collapsed.push(c);
+            last_was_space = false;
+        }
+    }
+
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_html, DomNode, Length, ParseOptions};
+
+    fn tags(node: &DomNode) -> Vec<&str> {
+        node.children
+            .iter()
+            .map(|child| child.tag_name.as_str())
+            .filter(|tag| *tag != "text")
+            .collect()
+    }
+
+    #[test]
+    fn legacy_file_without_body_uses_the_implicit_one() {
+        let source = r#"
+            <config><window width="250" height="120"/></config>
+            <var name="greeting" value="Hola"/>
+            <style>h1 { color: red; }</style>
+            <h1>{{ greeting }}</h1>
+            <p>Sin body</p>
+        "#;
+        let output = parse_html(source, &ParseOptions::default()).unwrap();
+
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+        assert_eq!(output.widgets.len(), 1);
+        let widget = &output.widgets[0];
+        assert_eq!(widget.id, "main");
+        assert_eq!(widget.config.width, Length::Px(250.0));
+        assert_eq!(tags(&widget.body), ["style", "h1", "p"]);
+        assert_eq!(widget.body.children[1].children[0].text_content.as_deref(), Some("Hola"));
+    }
+
+    #[test]
+    fn file_without_content_has_no_widget() {
+        let source = r#"<config><window width="250"/></config>"#;
+        assert!(parse_html(source, &ParseOptions::default()).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::diagnostics::{Diagnostics, ParseError};
+use super::html_parser::{build_dom_node, parse_document, DomNode};
+use super::variables::WidgetData;
+
+/// Sustituye `<include src="..."/>` por los nodos del fragmento, resolviendo
+/// la ruta relativa al archivo que lo incluye. Los fragmentos fuera de la
+/// carpeta del widget necesitan permiso en `data.permissions`. `stack`
+/// contiene los archivos que se están expandiendo para detectar inclusiones
+/// cíclicas.
+pub(crate) fn expand_includes(
+    node: &mut DomNode,
+    file: Option<&Path>,
+    data: &WidgetData,
+    diagnostics: &mut Diagnostics,
+    stack: &mut Vec<PathBuf>,
+) -> Result<(), ParseError> {
+    let mut children = Vec::with_capacity(node.children.len());
+
+    for mut child in std::mem::take(&mut node.children) {
+        if child.tag_name == "include" {
+            children.extend(load_fragment(&child, file, data, diagnostics, stack)?);
+        } else {
+            expand_includes(&mut child, file, data, diagnostics, stack)?;
+            children.push(child);
+        }
+    }
+
+    node.children = children;
+    Ok(())
+}
+
+fn load_fragment(
+    include: &DomNode,
+    file: Option<&Path>,
+    data: &WidgetData,
+    diagnostics: &mut Diagnostics,
+    stack: &mut Vec<PathBuf>,
+) -> Result<Vec<DomNode>, ParseError> {
+    let Some(src) = include.attributes.get("src") else {
+        return Err(diagnostics.error(include.span, "<include> necesita el atributo 'src'"));
+    };
+
+    let base_dir = file.and_then(Path::parent).unwrap_or(Path::new(""));
+    let path = base_dir.join(src);
+
+    // Lo que importa es la carpeta del widget, no el fragmento que incluye
+    match data.check_read(&path) {
+        Ok(true) => {}
+        Ok(false) => return Ok(Vec::new()),
+        Err(denied) => {
+            diagnostics.warning(include.span, format!("permiso denegado para el fragmento '{}': {}", src, denied));
+            return Ok(Vec::new());
+        }
+    }
+
+    let canonical = path.canonicalize().map_err(|e| {
+        diagnostics.error(
+            include.span,
+            format!("no se pudo abrir el fragmento '{}': {}", path.display(), e),
+        )
+    })?;
+
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(diagnostics.error(
+            include.span,
+            format!("inclusión cíclica: {}", chain.join(" -> ")),
+        ));
+    }
+
+    let source = fs::read_to_string(&canonical).map_err(|e| {
+        diagnostics.error(
+            include.span,
+            format!("no se pudo leer el fragmento '{}': {}", path.display(), e),
+        )
+    })?;
+
+    // Un fragmento es una lista de nodos sueltos: html5ever los deja en el <body> implícito
+    let (dom, source_map) = parse_document(&source);
+    let mut fragment = match dom.select_first("body") {
+        Ok(root) => build_dom_node(root.as_node(), &source_map),
+        Err(()) => return Ok(Vec::new()),
+    };
+
+    let params: HashMap<String, String> = include
+        .attributes
+        .iter()
+        .filter(|(name, _)| name.as_str() != "src")
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    substitute_params(&mut fragment, &params);
+
+    let mut fragment_diagnostics = Diagnostics::new(&source, Some(&path));
+    stack.push(canonical);
+    let result = expand_includes(&mut fragment, Some(&path), data, &mut fragment_diagnostics, stack);
+    stack.pop();
+
+    match result {
+        Ok(()) => {
+            diagnostics.extend(fragment_diagnostics.into_warnings());
+            Ok(fragment.children)
+        }
+        Err(err) => Err(diagnostics.propagate(err)),
+    }
+}
+
+/// Reemplaza `{nombre}` por el valor del parámetro en textos y atributos.
+/// Los nombres sin parámetro y las variables `{{ ... }}` se dejan intactos.
+pub(crate) fn substitute_params(node: &mut DomNode, params: &HashMap<String, String>) {
+    if params.is_empty() {
+        return;
+    }
+
+    if let Some(text) = &node.text_content {
+        node.text_content = Some(replace_params(text, params));
+    }
+
+    for value in node.attributes.values_mut() {
+        *value = replace_params(value, params);
+    }
+
+    for child in &mut node.children {
+        substitute_params(child, params);
+    }
+}
+
+/// Nombres de parámetro `{nombre}` usados en textos y atributos del árbol.
+pub(crate) fn referenced_params(node: &DomNode, names: &mut HashSet<String>) {
+    let mut record = |input: &str| {
+        replace_with(input, |name| {
+            names.insert(name.to_string());
+            None
+        });
+    };
+
+    if let Some(text) = &node.text_content {
+        record(text);
+    }
+    for value in node.attributes.values() {
+        record(value);
+    }
+
+    for child in &node.children {
+        referenced_params(child, names);
+    }
+}
+
+fn replace_params(input: &str, params: &HashMap<String, String>) -> String {
+    replace_with(input, |name| params.get(name).cloned())
+}
+
+// Recorre los `{nombre}` de `input` y sustituye los que `resolve` conoce
+fn replace_with(input: &str, mut resolve: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(open) = rest.find('{') {
+        output.push_str(&rest[..open]);
+        let after = &rest[open..];
+
+        if after.starts_with("{{") {
+            let end = after.find("}}").map(|i| i + 2).unwrap_or(after.len());
+            output.push_str(&after[..end]);
+            rest = &after[end..];
+            continue;
+        }
+
+        let value = after[1..]
+            .find('}')
+            .filter(|&close| is_param_name(&after[1..close + 1]))
+            .and_then(|close| resolve(&after[1..close + 1]).map(|value| (close, value)));
+
+        match value {
+            Some((close, value)) => {
+                output.push_str(&value);
+                rest = &after[close + 2..];
+            }
+            None => {
+                output.push('{');
+                rest = &after[1..];
+            }
+        }
+    }
+
+    output.push_str(rest);
+    output
+}
+
+fn is_param_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html_parser::{parse_html, ParseOptions};
+
+    // Un fragmento en la carpeta del widget `w`, otro en la del widget `other`
+    // y otro fuera de `widgets/`. La carpeta se borra al acabar el test,
+    // aunque falle
+    struct Project(PathBuf);
+
+    impl Project {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("ytml-include-{}-{}", name, std::process::id()));
+            for widget in ["w", "other"] {
+                fs::create_dir_all(root.join("widgets").join(widget)).unwrap();
+            }
+            fs::write(root.join("widgets").join("w").join("inside.ytml"), "<p>dentro</p>").unwrap();
+            fs::write(root.join("widgets").join("other").join("inside.ytml"), "<p>de otro</p>").unwrap();
+            fs::write(root.join("outside.ytml"), "<p>fuera</p>").unwrap();
+            Project(root)
+        }
+    }
+
+    impl Drop for Project {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn texts(node: &DomNode, output: &mut Vec<String>) {
+        output.extend(node.text_content.clone());
+        for child in &node.children {
+            texts(child, output);
+        }
+    }
+
+    fn included(root: &Path, permissions: &str, trusted: bool) -> (Vec<String>, Vec<String>) {
+        let source = format!(
+            r#"<widget id="w">{}<body><include src="w/inside.ytml"/><include src="other/inside.ytml"/><include src="../outside.ytml"/></body></widget>"#,
+            permissions
+        );
+        let options = ParseOptions {
+            file: Some(root.join("widgets").join("w.ytml")),
+            trusted,
+            ..Default::default()
+        };
+        let output = parse_html(&source, &options).unwrap();
+
+        let mut body = Vec::new();
+        texts(&output.widgets[0].body, &mut body);
+        let warnings = output.warnings.iter().map(|warning| warning.message.clone()).collect();
+        (body, warnings)
+    }
+
+    #[test]
+    fn fragments_outside_the_widget_folder_need_read_permission() {
+        let project = Project::new("denied");
+        let (body, warnings) = included(&project.0, "", true);
+
+        assert_eq!(body, ["dentro"]);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("permiso denegado para el fragmento 'other/inside.ytml'"));
+        assert!(warnings[1].starts_with("permiso denegado para el fragmento '../outside.ytml'"));
+    }
+
+    #[test]
+    fn declared_fragments_are_included() {
+        let project = Project::new("allowed");
+        let (body, warnings) = included(&project.0, r#"<permissions><read path="../outside.ytml"/></permissions>"#, true);
+
+        assert_eq!(body, ["dentro", "fuera"]);
+        // La carpeta de otro widget sigue necesitando su propio <read>
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with("permiso denegado para el fragmento 'other/inside.ytml'"));
+    }
+
+    #[test]
+    fn declared_fragments_wait_until_the_permissions_are_approved() {
+        let project = Project::new("pending");
+        let (body, warnings) = included(&project.0, r#"<permissions><read path="../outside.ytml"/></permissions>"#, false);
+
+        // Sin aprobar no se lee ni se avisa; lo que no está declarado sí se avisa
+        assert_eq!(body, ["dentro"]);
+        assert_eq!(warnings.len(), 1, "{:?}", warnings);
+        assert!(warnings[0].starts_with("permiso denegado para el fragmento 'other/inside.ytml'"));
+    }
+}
@@ -0,0 +1,269 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+use super::diagnostics::{Diagnostics, ParseError, Span};
+use super::directives::evaluate;
+use super::html_parser::{prepare_body, DomNode, ParseOptions, ParseOutput, WidgetDefinition, WindowConfig};
+use super::permissions::Permissions;
+use super::template::Templates;
+use super::variables::WidgetData;
+
+/// Formatos de intercambio para definir widgets desde otras herramientas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Toml,
+}
+
+impl DataFormat {
+    /// Formato de un archivo `*.ytml.json` o `*.ytml.toml`.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let file_name = path.file_name()?.to_str()?;
+
+        if file_name.ends_with(".ytml.json") {
+            Some(DataFormat::Json)
+        } else if file_name.ends_with(".ytml.toml") {
+            Some(DataFormat::Toml)
+        } else {
+            None
+        }
+    }
+}
+
+/// Nombre de un archivo de widgets sin `.ytml`, `.ytml.json` ni `.ytml.toml`:
+/// `reloj.ytml.json` es `reloj`.
+pub fn widget_file_stem(path: &Path) -> Option<&str> {
+    let file_name = path.file_name()?.to_str()?;
+    [".ytml.json", ".ytml.toml", ".ytml"]
+        .iter()
+        .find_map(|extension| file_name.strip_suffix(extension))
+        .or_else(|| path.file_stem()?.to_str())
+}
+
+#[derive(Serialize)]
+struct WidgetFile<'a> {
+    widgets: &'a [WidgetDefinition],
+}
+
+/// Exporta los widgets como `{ "widgets": [...] }`, que se puede volver a importar.
+///
+/// Como body se escribe el template sin evaluar, precedido de sus `<var>` y
+/// `<data>`, para que al importarlo se vuelva a evaluar igual que el original.
+pub fn export_widgets(widgets: &[WidgetDefinition], format: DataFormat) -> Result<String, String> {
+    let widgets: Vec<WidgetDefinition> = widgets
+        .iter()
+        .cloned()
+        .map(|mut widget| {
+            widget.body = widget.template.clone();
+            widget.body.children.splice(0..0, widget.data.declarations.iter().cloned());
+            widget
+        })
+        .collect();
+    let file = WidgetFile { widgets: &widgets };
+
+    match format {
+        DataFormat::Json => serde_json::to_string_pretty(&file).map_err(|e| e.to_string()),
+        DataFormat::Toml => toml::to_string_pretty(&file).map_err(|e| e.to_string()),
+    }
+}
+
+/// Importa widgets de JSON o TOML.
+///
+/// El documento es un widget (`id`, `config`, `permissions`, `body`) o una
+/// lista de ellos en `widgets`. La `config` se aplica sobre `options.defaults`
+/// y el body pasa por los mismos pasos que el de un YTML: `<include>`,
+/// componentes, `<var>`/`<data>`, directivas y `{{ }}`.
+pub fn import_widgets(source: &str, format: DataFormat, options: &ParseOptions) -> Result<ParseOutput, ParseError> {
+    let mut diagnostics = Diagnostics::new(source, options.file.as_deref());
+
+    let document: Value = match format {
+        DataFormat::Json => serde_json::from_str(source).map_err(|e| {
+            let span = (e.line() > 0).then(|| Span {
+                line: e.line(),
+                column: e.column(),
+            });
+            diagnostics.error(span, format!("JSON inválido: {}", e))
+        })?,
+        DataFormat::Toml => toml::from_str(source).map_err(|e| {
+            let span = e.span().map(|range| span_at(source, range.start));
+            diagnostics.error(span, format!("TOML inválido: {}", e.message()))
+        })?,
+    };
+
+    let entries = match document {
+        Value::Object(mut fields) if fields.contains_key("widgets") => match fields.remove("widgets") {
+            Some(Value::Array(entries)) => entries,
+            _ => return Err(diagnostics.error(None, "'widgets' debe ser una lista de widgets")),
+        },
+        widget => vec![widget],
+    };
+
+    let mut include_stack: Vec<PathBuf> = options
+        .file
+        .iter()
+        .filter_map(|file| file.canonicalize().ok())
+        .collect();
+
+    let mut widgets = Vec::new();
+    for entry in entries {
+        if let Some(widget) = import_widget(entry, options, &mut diagnostics, &mut include_stack)? {
+            widgets.push(widget);
+        }
+    }
+
+    if widgets.is_empty() {
+        return Err(diagnostics.error(None, "no se encontró ningún widget con body"));
+    }
+
+    Ok(ParseOutput {
+        widgets,
+        warnings: diagnostics.into_warnings(),
+    })
+}
+
+fn import_widget(
+    entry: Value,
+    options: &ParseOptions,
+    diagnostics: &mut Diagnostics,
+    include_stack: &mut Vec<PathBuf>,
+) -> Result<Option<WidgetDefinition>, ParseError> {
+    let Value::Object(mut fields) = entry else {
+        return Err(diagnostics.error(None, "cada widget debe ser un objeto con id, config y body"));
+    };
+
+    // Igual que en el formato antiguo, sin id el widget toma el nombre del archivo
+    let id = match fields.remove("id") {
+        Some(Value::String(id)) => id,
+        Some(other) => return Err(diagnostics.error(None, format!("el id del widget debe ser texto, no {}", other))),
+        None => "main".to_string(),
+    };
+
+    let config = match fields.remove("config") {
+        Some(config) => import_config(config, &options.defaults, &id, diagnostics)?,
+        None => options.defaults.clone(),
+    };
+
+    let permissions: Permissions = match fields.remove("permissions") {
+        Some(permissions) => serde_json::from_value(permissions)
+            .map_err(|e| diagnostics.error(None, format!("permissions inválido en el widget '{}': {}", id, e)))?,
+        None => Permissions::default(),
+    };
+
+    let Some(body) = fields.remove("body") else {
+        diagnostics.warning(None, format!("el widget '{}' no tiene body y se ignora", id));
+        return Ok(None);
+    };
+    let body: DomNode = serde_json::from_value(body)
+        .map_err(|e| diagnostics.error(None, format!("body inválido en el widget '{}': {}", id, e)))?;
+
+    for key in fields.keys() {
+        diagnostics.warning(None, format!("campo desconocido '{}' en el widget '{}'", key, id));
+    }
+
+    let mut data = WidgetData {
+        file: options.file.clone(),
+        ..Default::default()
+    };
+    data.grant(&permissions, options.trusted);
+    let template = prepare_body(body, &Templates::new(), &mut data, options, diagnostics, include_stack)?;
+    let body = evaluate(&template, &data, diagnostics);
+
+    Ok(Some(WidgetDefinition {
+        id,
+        config,
+        body,
+        permissions,
+        template,
+        data,
+    }))
+}
+
+// Solo se sobrescriben las claves presentes, como en un bloque <config>
+fn import_config(
+    config: Value,
+    defaults: &WindowConfig,
+    id: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<WindowConfig, ParseError> {
+    let Value::Object(overrides) = config else {
+        return Err(diagnostics.error(None, format!("la config del widget '{}' debe ser un objeto", id)));
+    };
+
+    let mut merged = match serde_json::to_value(defaults) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    };
+
+    for (key, value) in overrides {
+        if merged.contains_key(&key) {
+            merged.insert(key, value);
+        } else {
+            diagnostics.warning(None, format!("clave de configuración desconocida '{}' en el widget '{}'", key, id));
+        }
+    }
+
+    serde_json::from_value(Value::Object(merged))
+        .map_err(|e| diagnostics.error(None, format!("config inválida en el widget '{}': {}", id, e)))
+}
+
+fn span_at(source: &str, offset: usize) -> Span {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+    Span {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_widgets, import_widgets, widget_file_stem, DataFormat};
+    use std::path::Path;
+    use crate::parser::html_parser::{parse_html, ParseOptions};
+
+    #[test]
+    fn import_of_export_gives_the_same_widget() {
+        let source = r#"
+            <widget id="servers">
+                <config><window width="300" height="200"/></config>
+                <permissions><network/></permissions>
+                <var name="title" value="Servidores"/>
+                <body>
+                    <data name="servers">[{"name": "a", "up": true}, {"name": "b", "up": false}]</data>
+                    <h1>{{ title }} \{{ literal }}</h1>
+                    <p for-each="s in servers" if="s.up">{{ s.name }}</p>
+                </body>
+            </widget>
+        "#;
+        let options = ParseOptions {
+            variables: [("title".to_string(), "Desde la CLI".to_string())].into(),
+            ..Default::default()
+        };
+        let widget = &parse_html(source, &options).unwrap().widgets[0];
+
+        for format in [DataFormat::Json, DataFormat::Toml] {
+            let exported = export_widgets(std::slice::from_ref(widget), format).unwrap();
+            let output = import_widgets(&exported, format, &options).unwrap();
+
+            assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+            assert!(!exported.contains("Desde la CLI"), "{}", exported);
+            let again = &output.widgets[0];
+            assert_eq!(again.id, widget.id);
+            assert_eq!(again.config, widget.config);
+            assert_eq!(again.permissions, widget.permissions);
+            assert_eq!(again.data.declarations, widget.data.declarations);
+            assert_eq!(again.template, widget.template);
+            assert_eq!(again.body, widget.body);
+        }
+    }
+
+    #[test]
+    fn file_stem_drops_the_interchange_extension() {
+        assert_eq!(widget_file_stem(Path::new("widgets/reloj.ytml")), Some("reloj"));
+        assert_eq!(widget_file_stem(Path::new("widgets/reloj.ytml.json")), Some("reloj"));
+        assert_eq!(widget_file_stem(Path::new("reloj.ytml.toml")), Some("reloj"));
+        assert_eq!(widget_file_stem(Path::new("notas.txt")), Some("notas"));
+    }
+}
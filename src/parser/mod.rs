@@ -1 +1,13 @@
+pub mod config;
+pub mod diagnostics;
+pub mod directives;
+mod expr;
+pub mod geometry;
 pub mod html_parser;
+mod include;
+pub mod interchange;
+pub mod permissions;
+pub mod serializer;
+mod source;
+mod template;
+pub mod variables;
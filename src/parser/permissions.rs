@@ -0,0 +1,384 @@
+use kuchiki::NodeRef;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use super::diagnostics::Diagnostics;
+use super::interchange::widget_file_stem;
+use super::source::SourceMap;
+
+const PERMISSION_KEYS: &[(&str, &[&str])] = &[
+    ("permissions", &[]),
+    ("exec", &["command"]),
+    ("read", &["path"]),
+    ("write", &["path"]),
+    ("network", &[]),
+];
+
+/// Programas que ejecutan lo que se les pase; declararlos da acceso completo.
+const SHELLS: &[&str] = &["sh", "bash", "zsh", "dash"];
+
+/// Lo que un widget declara en `<permissions>` que necesita. Todo lo que no
+/// aparece aquí se le deniega; lo que hay en la carpeta propia del widget, la
+/// que se llama como su archivo (`widgets/clock/` para `widgets/clock.ytml`),
+/// siempre se puede leer. El resto de `widgets/` es de los otros widgets.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Permissions {
+    /// Programas que puede ejecutar, tal y como se escriben en `exec:`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exec: Vec<String>,
+    /// Archivos o directorios que puede leer, relativos al archivo del widget o con `~`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub read: Vec<String>,
+    /// Archivos o directorios en los que puede escribir; también se pueden leer.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub write: Vec<String>,
+    /// Abrir URIs que no son archivos locales.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub network: bool,
+}
+
+/// Acción que el manifiesto no permite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Denied(String);
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} no está declarado en <permissions>", self.0)
+    }
+}
+
+impl std::error::Error for Denied {}
+
+impl Permissions {
+    pub fn is_empty(&self) -> bool {
+        *self == Permissions::default()
+    }
+
+    /// `argv[0]` tiene que estar en `exec` tal cual (`curl` no permite
+    /// `./curl`). Un programa puede modificar lo que recibe, así que todo lo
+    /// que un argumento pueda usar como ruta tiene que estar dentro de la
+    /// carpeta del widget o de `write`, y las URIs que no son archivos
+    /// necesitan `network`. Las órdenes se ejecutan en el directorio de
+    /// `file`, el archivo del widget, así que las rutas relativas parten de
+    /// ahí. Un shell declarado puede hacer cualquier cosa, así que sus
+    /// argumentos no se revisan.
+    pub fn check_exec(&self, argv: &[String], file: Option<&Path>) -> Result<(), Denied> {
+        let Some(program) = argv.first() else {
+            return Ok(());
+        };
+        if !self.exec.iter().any(|allowed| allowed == program) {
+            return Err(Denied(format!("ejecutar \"{}\"", program)));
+        }
+        if SHELLS.contains(&program.as_str()) {
+            return Ok(());
+        }
+
+        let cwd = file.and_then(Path::parent).map(Path::to_path_buf).or_else(|| std::env::current_dir().ok());
+        for value in argv.iter().skip(1).flat_map(|arg| path_candidates(arg)) {
+            let path = match value.strip_prefix("file://") {
+                Some(path) => PathBuf::from(path),
+                None if is_remote_uri(value) && self.network => continue,
+                None if is_remote_uri(value) => {
+                    return Err(Denied(format!("usar la red con \"{}\" ({})", program, value)))
+                }
+                None => resolve(value, cwd.as_deref()),
+            };
+            if !self.can_write(&path, file) {
+                return Err(Denied(format!("pasar la ruta {} a \"{}\" sin <write>", path.display(), program)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Leer un archivo fuera de la carpeta del widget de `file`.
+    pub fn check_read(&self, path: &Path, file: Option<&Path>) -> Result<(), Denied> {
+        if self.can_read(path, file) {
+            Ok(())
+        } else {
+            Err(Denied(format!("leer {}", path.display())))
+        }
+    }
+
+    /// Abrir una URI: `file://` es leer ese archivo y el resto, usar la red.
+    pub fn check_uri(&self, uri: &str, file: Option<&Path>) -> Result<(), Denied> {
+        match uri.strip_prefix("file://") {
+            Some(path) => self.check_read(Path::new(path), file),
+            None if self.network => Ok(()),
+            None => Err(Denied(format!("usar la red para abrir {}", uri))),
+        }
+    }
+
+    /// Una línea por permiso, para enseñárselas al usuario.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for program in &self.exec {
+            if SHELLS.contains(&program.as_str()) {
+                lines.push(format!("Ejecutar scripts de shell con {} (acceso completo)", program));
+            } else {
+                lines.push(format!("Ejecutar {}", program));
+            }
+        }
+        lines.extend(self.read.iter().map(|path| format!("Leer {}", path)));
+        lines.extend(self.write.iter().map(|path| format!("Leer y modificar {}", path)));
+        if self.network {
+            lines.push("Abrir direcciones de internet y pasárselas a los programas".to_string());
+        }
+        lines
+    }
+
+    // Lo que lee ytml: `read`, `write` y la carpeta del widget
+    fn can_read(&self, path: &Path, file: Option<&Path>) -> bool {
+        self.can_write(path, file) || inside_any(path, &self.read, file.and_then(Path::parent))
+    }
+
+    // Lo que se les pasa a los programas: `write` y la carpeta del widget
+    fn can_write(&self, path: &Path, file: Option<&Path>) -> bool {
+        own_folder(file).is_some_and(|folder| canonical(path).starts_with(canonical(&folder)))
+            || inside_any(path, &self.write, file.and_then(Path::parent))
+    }
+}
+
+/// Carpeta propia del widget de `file`: junto a él y con su nombre sin
+/// extensión. Las rutas relativas del widget parten del directorio de `file`,
+/// así que dentro del widget es `clock/...`.
+pub fn own_folder(file: Option<&Path>) -> Option<PathBuf> {
+    let file = file?;
+    Some(file.parent()?.join(widget_file_stem(file)?))
+}
+
+/// Lee un bloque `<permissions>` avisando de lo que no se reconoce.
+pub(crate) fn read_permissions(
+    permissions_node: &NodeRef,
+    source_map: &SourceMap,
+    diagnostics: &mut Diagnostics,
+) -> Permissions {
+    let mut permissions = Permissions::default();
+
+    for node in permissions_node.inclusive_descendants() {
+        let Some(element) = node.as_element() else {
+            continue;
+        };
+        let span = source_map.span_of(&node);
+        let tag = element.name.local.to_string();
+        let attributes = element.attributes.borrow();
+
+        let Some((_, known_attrs)) = PERMISSION_KEYS.iter().find(|(key, _)| *key == tag) else {
+            diagnostics.warning(span, format!("permiso desconocido <{}>", tag));
+            continue;
+        };
+        for (name, _) in attributes.map.iter() {
+            if !known_attrs.contains(&&*name.local) {
+                diagnostics.warning(span, format!("atributo desconocido '{}' en <{}>", name.local, tag));
+            }
+        }
+
+        let value = known_attrs.first().map(|name| attributes.get(*name).map(str::trim));
+        match (tag.as_str(), value) {
+            ("network", _) => permissions.network = true,
+            (_, Some(Some(value))) if !value.is_empty() => {
+                let list = match tag.as_str() {
+                    "exec" => &mut permissions.exec,
+                    "read" => &mut permissions.read,
+                    _ => &mut permissions.write,
+                };
+                list.push(value.to_string());
+            }
+            (_, Some(_)) => diagnostics.warning(
+                span,
+                format!("<{}> necesita el atributo '{}'", tag, known_attrs[0]),
+            ),
+            _ => {}
+        }
+    }
+
+    permissions
+}
+
+// `path` está dentro de alguna de las rutas declaradas
+fn inside_any(path: &Path, declared: &[String], base: Option<&Path>) -> bool {
+    let path = canonical(path);
+    declared.iter().any(|declared| path.starts_with(canonical(&resolve(declared, base))))
+}
+
+// Lo que un programa podría leer como ruta en un argumento: el argumento
+// entero, el valor de `--opcion=valor`, `-ovalor` o `clave=valor`, y sin
+// prefijos como el `@archivo` de curl. Con tantos candidatos alguno no será
+// una ruta, pero dentro de la carpeta del widget cualquier texto lo es
+fn path_candidates(arg: &str) -> Vec<&str> {
+    let value = match arg.strip_prefix('-') {
+        Some(option) if option.starts_with('-') => option.split_once('=').map_or("", |(_, value)| value),
+        Some(option) => option.char_indices().nth(1).map_or("", |(index, _)| &option[index..]),
+        None => arg,
+    };
+
+    let mut candidates = vec![value];
+    if let Some((_, assigned)) = value.split_once('=') {
+        candidates.push(assigned);
+    }
+    candidates.push(value.trim_start_matches(['@', '<', '>']));
+
+    candidates.retain(|candidate| !candidate.is_empty());
+    candidates
+}
+
+// `https://...`, `ftp://...`: lo que no es un archivo local
+fn is_remote_uri(value: &str) -> bool {
+    value.split_once("://").is_some_and(|(scheme, _)| {
+        !scheme.is_empty() && scheme != "file" && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
+
+// `~` es el directorio personal y lo relativo cuelga de `base`
+fn resolve(path: &str, base: Option<&Path>) -> PathBuf {
+    let home = || std::env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+    if path == "~" {
+        return home();
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        return home().join(rest);
+    }
+    base.map_or_else(|| PathBuf::from(path), |base| base.join(path))
+}
+
+// Sigue los enlaces si la ruta existe; si no, al menos quita los `..` para
+// que `dir/../otro` no pase por estar dentro de `dir`. Lo relativo parte del
+// directorio de trabajo
+fn canonical(path: &Path) -> PathBuf {
+    // `Path::parent` de un nombre suelto es la ruta vacía
+    let path = if path.as_os_str().is_empty() { Path::new(".") } else { path };
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "/nonexistent-ytml/widgets/clock.ytml";
+
+    fn permissions() -> Permissions {
+        Permissions {
+            exec: vec!["cat".to_string(), "curl".to_string(), "sh".to_string()],
+            read: vec!["/nonexistent-ytml/notes".to_string()],
+            write: vec!["/nonexistent-ytml/out".to_string()],
+            network: false,
+        }
+    }
+
+    fn check_exec(command: &str) -> Result<(), Denied> {
+        let argv: Vec<String> = command.split(' ').map(str::to_string).collect();
+        permissions().check_exec(&argv, Some(Path::new(FILE)))
+    }
+
+    #[test]
+    fn exec_needs_the_program_declared_as_written() {
+        assert!(check_exec("cat clock/notes.txt").is_ok());
+        assert!(check_exec("rm clock/notes.txt").is_err());
+        assert!(check_exec("./cat clock/notes.txt").is_err());
+        assert!(check_exec("/usr/bin/cat clock/notes.txt").is_err());
+    }
+
+    #[test]
+    fn exec_checks_every_argument_as_a_path() {
+        assert!(check_exec("cat /nonexistent-ytml/out/today.txt").is_ok());
+        assert!(check_exec("cat clock/today.txt").is_ok());
+        assert!(check_exec("cat data/today.txt").is_err());
+        assert!(check_exec("cat /nonexistent-ytml/secret").is_err());
+        assert!(check_exec("cat ../secret").is_err());
+        assert!(check_exec("cat data/../../secret").is_err());
+        assert!(check_exec("cat file:///nonexistent-ytml/secret").is_err());
+    }
+
+    #[test]
+    fn exec_checks_option_values() {
+        assert!(check_exec("curl -s -o /nonexistent-ytml/out/page.html").is_ok());
+        assert!(check_exec("curl --output=/nonexistent-ytml/secret").is_err());
+        assert!(check_exec("curl -o/nonexistent-ytml/secret").is_err());
+        assert!(check_exec("curl -d @../secret").is_err());
+        assert!(check_exec("cat if=/nonexistent-ytml/secret").is_err());
+        assert!(check_exec("curl --silent -").is_ok());
+    }
+
+    #[test]
+    fn exec_arguments_need_write_not_read() {
+        assert!(check_exec("cat /nonexistent-ytml/notes/today.txt").is_err());
+        assert!(check_exec("cat /nonexistent-ytml/widgets/clock/today.txt").is_ok());
+    }
+
+    #[test]
+    fn exec_remote_uris_need_network() {
+        assert!(check_exec("curl -s https://example.com").is_err());
+
+        let mut permissions = permissions();
+        permissions.network = true;
+        let argv: Vec<String> = ["curl", "-s", "https://example.com"].map(str::to_string).to_vec();
+        assert!(permissions.check_exec(&argv, Some(Path::new(FILE))).is_ok());
+    }
+
+    #[test]
+    fn exec_of_a_declared_shell_is_not_inspected() {
+        assert!(check_exec("sh -c cat /nonexistent-ytml/secret").is_ok());
+    }
+
+    #[test]
+    fn read_allows_the_widget_folder_and_declared_paths() {
+        let permissions = permissions();
+        let file = Some(Path::new(FILE));
+        let check = |path: &str| permissions.check_read(Path::new(path), file);
+
+        assert!(check("/nonexistent-ytml/widgets/clock/data.json").is_ok());
+        assert!(check("/nonexistent-ytml/notes/a/b.json").is_ok());
+        assert!(check("/nonexistent-ytml/out/result.json").is_ok());
+        assert!(check("/nonexistent-ytml/notes-old/a.json").is_err());
+        assert!(check("/nonexistent-ytml/widgets/clock/../secret.json").is_err());
+        assert!(Permissions::default().check_read(Path::new("/nonexistent-ytml/a"), None).is_err());
+    }
+
+    #[test]
+    fn other_widgets_files_are_denied() {
+        let permissions = Permissions::default();
+        let file = Some(Path::new(FILE));
+        let check = |path: &str| permissions.check_read(Path::new(path), file);
+
+        assert!(check("/nonexistent-ytml/widgets/clock/today.txt").is_ok());
+        assert!(check("/nonexistent-ytml/widgets/weather/today.txt").is_err());
+        assert!(check("/nonexistent-ytml/widgets/weather.ytml").is_err());
+        assert!(check("/nonexistent-ytml/widgets/clock-old/today.txt").is_err());
+
+        let argv: Vec<String> = ["cat", "weather/today.txt"].map(str::to_string).to_vec();
+        let exec = Permissions { exec: vec!["cat".to_string()], ..Permissions::default() };
+        assert!(exec.check_exec(&argv, file).is_err());
+
+        // `clock.ytml.json` también tiene la carpeta `clock`
+        let json = Some(Path::new("/nonexistent-ytml/widgets/clock.ytml.json"));
+        assert_eq!(own_folder(json), Some(PathBuf::from("/nonexistent-ytml/widgets/clock")));
+    }
+
+    #[test]
+    fn uri_needs_network_unless_it_is_a_readable_file() {
+        let mut permissions = permissions();
+        let file = Some(Path::new(FILE));
+
+        assert!(permissions.check_uri("file:///nonexistent-ytml/notes/a.txt", file).is_ok());
+        assert!(permissions.check_uri("file:///nonexistent-ytml/secret", file).is_err());
+        assert!(permissions.check_uri("https://example.com", file).is_err());
+
+        permissions.network = true;
+        assert!(permissions.check_uri("https://example.com", file).is_ok());
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt::Write;
+
+use super::html_parser::{DomNode, WidgetDefinition, WindowConfig};
+use super::permissions::Permissions;
+
+/// Etiquetas cuyo contenido html5ever lee como texto, sin entidades.
+const RAW_TEXT_TAGS: &[&str] = &["style", "script"];
+
+const INDENT: &str = "    ";
+
+/// Escribe varios widgets en formato `<widget id="...">`, separados por una
+/// línea en blanco.
+pub fn serialize_widgets(widgets: &[WidgetDefinition]) -> String {
+    widgets
+        .iter()
+        .map(serialize_widget)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escribe el widget como YTML canónico: la configuración completa (incluidos
+/// los valores heredados), los permisos, sus `<var>`/`<data>` y el body sin
+/// evaluar, con los `for-each`, `if` y `{{ }}` tal cual. Los `<include>` y
+/// componentes se escriben ya expandidos, y las declaraciones de fuera del
+/// widget pasan a él.
+///
+/// Los atributos se ordenan alfabéticamente y la sangría solo se añade donde
+/// el parser la descarta, así que parsear el resultado devuelve el mismo `id`,
+/// `config`, permisos, declaraciones y `template`.
+pub fn serialize_widget(widget: &WidgetDefinition) -> String {
+    let mut output = String::new();
+
+    let _ = writeln!(output, "<widget id=\"{}\">", escape_attr(&widget.id));
+    write_config(&widget.config, &mut output);
+    write_permissions(&widget.permissions, &mut output);
+    for declaration in &widget.data.declarations {
+        output.push_str(INDENT);
+        write_node(declaration, 1, false, &mut output);
+        output.push('\n');
+    }
+    output.push_str(INDENT);
+    write_node(&widget.template, 1, false, &mut output);
+    output.push_str("\n</widget>\n");
+
+    output
+}
+
+fn write_config(config: &WindowConfig, output: &mut String) {
+    let _ = writeln!(output, "{}<config>", INDENT);
+
+    let _ = write!(
+        output,
+        "{0}{0}<window width=\"{1}\" height=\"{2}\"",
+        INDENT, config.width, config.height
+    );
+    if let Some(x) = config.x {
+        let _ = write!(output, " x=\"{}\"", x);
+    }
+    if let Some(y) = config.y {
+        let _ = write!(output, " y=\"{}\"", y);
+    }
+    let _ = write!(output, " anchor=\"{}\" margin=\"{}\"", config.anchor, config.margin);
+    output.push_str("/>\n");
+
+    for (tag, enabled) in [
+        ("decorations", config.decorations),
+        ("transparent", config.transparent),
+        ("resizable", config.resizable),
+    ] {
+        let _ = writeln!(output, "{0}{0}<{1} enabled=\"{2}\"/>", INDENT, tag, enabled);
+    }
+
+    let _ = writeln!(output, "{}</config>", INDENT);
+}
+
+fn write_permissions(permissions: &Permissions, output: &mut String) {
+    if permissions.is_empty() {
+        return;
+    }
+
+    let _ = writeln!(output, "{}<permissions>", INDENT);
+    for (tag, attribute, values) in [
+        ("exec", "command", &permissions.exec),
+        ("read", "path", &permissions.read),
+        ("write", "path", &permissions.write),
+    ] {
+        for value in values {
+            let _ = writeln!(output, "{0}{0}<{1} {2}=\"{3}\"/>", INDENT, tag, attribute, escape_attr(value));
+        }
+    }
+    if permissions.network {
+        let _ = writeln!(output, "{0}{0}<network/>", INDENT);
+    }
+    let _ = writeln!(output, "{}</permissions>", INDENT);
+}
+
+fn write_node(node: &DomNode, level: usize, preformatted: bool, output: &mut String) {
+    if node.tag_name == "text" {
+        write_text(node.text_content.as_deref().unwrap_or(""), false, output);
+        return;
+    }
+
+    output.push('<');
+    output.push_str(&node.tag_name);
+
+    let mut attributes: Vec<_> = node.attributes.iter().collect();
+    attributes.sort();
+    for (name, value) in attributes {
+        let _ = write!(output, " {}=\"{}\"", name, escape_attr(value));
+    }
+
+    if node.children.is_empty() {
+        output.push_str("/>");
+        return;
+    }
+    output.push('>');
+
+    let raw = RAW_TEXT_TAGS.contains(&node.tag_name.as_str());
+    let preformatted = preformatted || node.tag_name == "pre";
+
+    if preformatted || raw {
+        // html5ever se come el primer salto de línea tras `<pre>`
+        let starts_with_newline = node
+            .children
+            .first()
+            .and_then(|child| child.text_content.as_deref())
+            .is_some_and(|text| text.starts_with('\n'));
+        if node.tag_name == "pre" && starts_with_newline {
+            output.push('\n');
+        }
+
+        for child in &node.children {
+            match &child.text_content {
+                Some(text) => write_text(text, raw, output),
+                None => write_node(child, level + 1, preformatted, output),
+            }
+        }
+    } else if let [DomNode { text_content: Some(text), .. }] = node.children.as_slice() {
+        write_text(text, false, output);
+    } else {
+        // Los espacios del principio, del final y entre elementos se colapsan
+        // al parsear, así que ahí se puede sangrar sin cambiar el árbol
+        let child_indent = INDENT.repeat(level + 1);
+        let _ = write!(output, "\n{}", child_indent);
+
+        for child in &node.children {
+            match &child.text_content {
+                Some(text) if text.trim().is_empty() => {
+                    let _ = write!(output, "\n{}", child_indent);
+                }
+                _ => write_node(child, level + 1, false, output),
+            }
+        }
+
+        let _ = write!(output, "\n{}", INDENT.repeat(level));
+    }
+
+    let _ = write!(output, "</{}>", node.tag_name);
+}
+
+fn write_text(text: &str, raw: bool, output: &mut String) {
+    if raw {
+        output.push_str(text);
+    } else {
+        output.push_str(&text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;"));
+    }
+}
+
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::serialize_widgets;
+    use crate::parser::html_parser::{parse_html, ParseOptions, WidgetDefinition};
+
+    fn parse(source: &str, options: &ParseOptions) -> Vec<WidgetDefinition> {
+        let output = parse_html(source, options).unwrap();
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+        output.widgets
+    }
+
+    #[test]
+    fn parse_of_serialize_gives_the_same_widget() {
+        let source = r#"
+            <var name="title" value="Servidores"/>
+            <widget id="servers">
+                <config><window width="300" height="200"/></config>
+                <permissions><exec command="notify-send"/></permissions>
+                <data name="servers">[{"name": "a", "up": true}, {"name": "b", "up": false}]</data>
+                <body>
+                    <var name="limit" value="1"/>
+                    <style>p { color: red; }</style>
+                    <h1>{{ title }} &amp; \{{ literal }}</h1>
+                    <p for-each="s, i in servers" if="s.up" onclick="exec:notify-send {$value}">{{ i }}: {{ s.name }}</p>
+                    <pre>
+  {{ limit }}
+</pre>
+                </body>
+            </widget>
+        "#;
+        let options = ParseOptions {
+            variables: [("limit".to_string(), "9".to_string())].into(),
+            ..Default::default()
+        };
+        let widgets = parse(source, &options);
+        let serialized = serialize_widgets(&widgets);
+        let reparsed = parse(&serialized, &options);
+
+        // Los valores de línea de comandos no se quedan en el archivo
+        assert!(!serialized.contains('9'), "{}", serialized);
+        assert!(serialized.contains("for-each=\"s, i in servers\""), "{}", serialized);
+        assert_eq!(reparsed.len(), 1);
+        let (widget, again) = (&widgets[0], &reparsed[0]);
+        assert_eq!(again.id, widget.id);
+        assert_eq!(again.config, widget.config);
+        assert_eq!(again.permissions, widget.permissions);
+        assert_eq!(again.data.declarations, widget.data.declarations);
+        assert_eq!(again.template, widget.template);
+        assert_eq!(again.body, widget.body);
+        assert_eq!(serialize_widgets(&reparsed), serialized);
+    }
+}
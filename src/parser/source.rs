@@ -0,0 +1,201 @@
+use std::collections::{HashMap, VecDeque};
+
+use kuchiki::{Node, NodeRef};
+
+use super::diagnostics::Span;
+
+/// Etiquetas que html5ever trata de forma especial y que se renombran antes de
+/// parsear para conservarlas donde aparecen (p. ej. un `<body>` dentro de `<widget>`
+/// o los hijos de `<template>`, que html5ever guarda fuera del árbol).
+const RENAMED_TAGS: &[(&str, &str)] = &[("body", "ytml-body"), ("template", "ytml-template")];
+
+const VOID_TAGS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source",
+    "track", "wbr",
+];
+
+/// Nombre que recibe la etiqueta dentro del árbol de kuchiki.
+pub(crate) fn internal_tag_name(name: &str) -> &str {
+    RENAMED_TAGS
+        .iter()
+        .find(|(original, _)| *original == name)
+        .map(|(_, internal)| *internal)
+        .unwrap_or(name)
+}
+
+/// Nombre YTML original de una etiqueta renombrada por `normalize`.
+pub(crate) fn original_tag_name(name: &str) -> &str {
+    RENAMED_TAGS
+        .iter()
+        .find(|(_, internal)| *internal == name)
+        .map(|(original, _)| *original)
+        .unwrap_or(name)
+}
+
+/// Prepara el fuente YTML para html5ever: renombra las etiquetas de
+/// `RENAMED_TAGS` y expande `<tag/>` a `<tag></tag>` para que las etiquetas
+/// autocerradas no se traguen a sus hermanas.
+pub(crate) fn normalize(source: &str) -> String {
+    let mut output = String::with_capacity(source.len());
+    let mut pos = 0;
+
+    while let Some(found) = source[pos..].find('<') {
+        let start = pos + found;
+        output.push_str(&source[pos..start]);
+        let rest = &source[start + 1..];
+
+        if rest.starts_with("!--") {
+            let end = rest
+                .find("-->")
+                .map(|end| start + 1 + end + 3)
+                .unwrap_or(source.len());
+            output.push_str(&source[start..end]);
+            pos = end;
+            continue;
+        }
+
+        let closing = rest.starts_with('/');
+        let name_start = if closing { 1 } else { 0 };
+        let name: String = rest[name_start..]
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+            .collect();
+
+        if name.is_empty() || !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            output.push('<');
+            pos = start + 1;
+            continue;
+        }
+
+        let lower = name.to_ascii_lowercase();
+        let internal = internal_tag_name(&lower);
+        let after_name = start + 1 + name_start + name.len();
+        let Some(tag_end) = find_tag_end(source, after_name) else {
+            output.push_str(&source[start..]);
+            pos = source.len();
+            break;
+        };
+
+        let attrs = &source[after_name..tag_end];
+        output.push('<');
+        if closing {
+            output.push('/');
+        }
+        output.push_str(internal);
+
+        match attrs.trim_end().strip_suffix('/') {
+            Some(attrs) if !closing && !VOID_TAGS.contains(&lower.as_str()) => {
+                output.push_str(attrs);
+                output.push_str("></");
+                output.push_str(internal);
+                output.push('>');
+            }
+            _ => {
+                output.push_str(attrs);
+                output.push('>');
+            }
+        }
+
+        pos = tag_end + 1;
+    }
+
+    output.push_str(&source[pos..]);
+    output
+}
+
+// Posición del `>` que cierra la etiqueta, ignorando los que van entre comillas
+fn find_tag_end(source: &str, from: usize) -> Option<usize> {
+    let mut quote = None;
+
+    for (i, c) in source[from..].char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            (None, '>') => return Some(from + i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Relaciona los nodos de kuchiki con su posición en el texto original.
+///
+/// html5ever no expone posiciones, así que se escanean las etiquetas de apertura
+/// del fuente y se asignan, en orden de documento, a los elementos con el mismo
+/// nombre. Los elementos implícitos (`html`, `head`...) se quedan sin span.
+pub(crate) struct SourceMap {
+    spans: HashMap<*const Node, Span>,
+}
+
+impl SourceMap {
+    pub fn build(source: &str, dom: &NodeRef) -> Self {
+        let mut open_tags = scan_start_tags(source);
+        let mut spans = HashMap::new();
+
+        for node in dom.inclusive_descendants() {
+            if let Some(element) = node.as_element() {
+                let name = element.name.local.to_string();
+                if let Some(span) = open_tags.get_mut(&name).and_then(VecDeque::pop_front) {
+                    spans.insert(&*node as *const Node, span);
+                }
+            }
+        }
+
+        SourceMap { spans }
+    }
+
+    /// Span del nodo; los nodos de texto heredan el de su elemento padre.
+    pub fn span_of(&self, node: &NodeRef) -> Option<Span> {
+        if let Some(span) = self.spans.get(&(&**node as *const Node)) {
+            return Some(*span);
+        }
+        if node.as_element().is_none() {
+            return node.parent().and_then(|parent| self.span_of(&parent));
+        }
+        None
+    }
+}
+
+fn scan_start_tags(source: &str) -> HashMap<String, VecDeque<Span>> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+
+    let span_at = |offset: usize| {
+        let line = line_starts.partition_point(|&start| start <= offset);
+        let column = source[line_starts[line - 1]..offset].chars().count() + 1;
+        Span { line, column }
+    };
+
+    let mut tags: HashMap<String, VecDeque<Span>> = HashMap::new();
+    let mut pos = 0;
+
+    while let Some(found) = source[pos..].find('<') {
+        let start = pos + found;
+        let rest = &source[start + 1..];
+
+        // Saltar comentarios completos para no registrar etiquetas comentadas
+        if rest.starts_with("!--") {
+            pos = rest
+                .find("-->")
+                .map(|end| start + 1 + end + 3)
+                .unwrap_or(source.len());
+            continue;
+        }
+
+        if rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let name: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':'))
+                .collect();
+            tags.entry(internal_tag_name(&name.to_ascii_lowercase()).to_string())
+                .or_default()
+                .push_back(span_at(start));
+        }
+
+        pos = start + 1;
+    }
+
+    tags
+}
@@ -0,0 +1,202 @@
+use std::collections::{HashMap, HashSet};
+
+use super::diagnostics::{Diagnostics, ParseError};
+use super::html_parser::DomNode;
+use super::include::{referenced_params, substitute_params};
+
+/// Componentes definidos con `<template name="...">`, indexados por nombre.
+///
+/// Las props son los `{nombre}` usados en la plantilla; los atributos de
+/// `<template>` (salvo `name`) declaran props con su valor por defecto.
+pub(crate) type Templates = HashMap<String, DomNode>;
+
+/// Extrae los `<template>` del árbol y los registra en `templates`.
+pub(crate) fn collect_templates(
+    node: &mut DomNode,
+    templates: &mut Templates,
+    diagnostics: &mut Diagnostics,
+) {
+    let mut children = Vec::with_capacity(node.children.len());
+
+    for mut child in std::mem::take(&mut node.children) {
+        if child.tag_name == "template" {
+            register_template(child, templates, diagnostics);
+        } else {
+            collect_templates(&mut child, templates, diagnostics);
+            children.push(child);
+        }
+    }
+
+    node.children = children;
+}
+
+pub(crate) fn register_template(
+    template: DomNode,
+    templates: &mut Templates,
+    diagnostics: &mut Diagnostics,
+) {
+    match template.attributes.get("name") {
+        Some(name) => {
+            if templates.contains_key(name) {
+                diagnostics.warning(
+                    template.span,
+                    format!("el componente '{}' ya estaba definido y se reemplaza", name),
+                );
+            }
+            templates.insert(name.clone(), template);
+        }
+        None => diagnostics.warning(template.span, "<template> sin atributo 'name' se ignora"),
+    }
+}
+
+/// Sustituye cada uso de un componente por su plantilla, con las props y los
+/// slots resueltos. `stack` evita componentes que se usan a sí mismos.
+pub(crate) fn expand_components(
+    node: &mut DomNode,
+    templates: &Templates,
+    diagnostics: &mut Diagnostics,
+    stack: &mut Vec<String>,
+) -> Result<(), ParseError> {
+    let mut children = Vec::with_capacity(node.children.len());
+
+    for mut child in std::mem::take(&mut node.children) {
+        match templates.get(&child.tag_name) {
+            Some(template) => {
+                let name = child.tag_name.clone();
+                if stack.contains(&name) {
+                    return Err(diagnostics.error(
+                        child.span,
+                        format!("el componente '{}' se usa a sí mismo", name),
+                    ));
+                }
+
+                let mut expanded = instantiate(template, child, diagnostics);
+                stack.push(name);
+                let result = expand_components(&mut expanded, templates, diagnostics, stack);
+                stack.pop();
+                result?;
+
+                children.extend(expanded.children);
+            }
+            None => {
+                expand_components(&mut child, templates, diagnostics, stack)?;
+                children.push(child);
+            }
+        }
+    }
+
+    node.children = children;
+    Ok(())
+}
+
+// Devuelve una copia de la plantilla (como nodo raíz) con props y slots aplicados
+fn instantiate(template: &DomNode, usage: DomNode, diagnostics: &mut Diagnostics) -> DomNode {
+    let mut expanded = template.clone();
+    expanded.attributes.clear();
+
+    let mut referenced = HashSet::new();
+    referenced_params(&expanded, &mut referenced);
+
+    let mut props: HashMap<String, String> = template
+        .attributes
+        .iter()
+        .filter(|(name, _)| name.as_str() != "name")
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+
+    for (name, value) in &usage.attributes {
+        if props.contains_key(name) || referenced.contains(name) {
+            props.insert(name.clone(), value.clone());
+        } else {
+            diagnostics.warning(
+                usage.span,
+                format!("prop desconocida '{}' en <{}>", name, usage.tag_name),
+            );
+        }
+    }
+
+    for name in referenced {
+        if !props.contains_key(&name) {
+            diagnostics.warning(
+                usage.span,
+                format!("falta la prop '{}' en <{}>, se deja vacía", name, usage.tag_name),
+            );
+            props.insert(name, String::new());
+        }
+    }
+
+    substitute_params(&mut expanded, &props);
+
+    // Repartir los hijos del uso entre el slot por defecto y los slots con nombre
+    let mut slots: HashMap<String, Vec<DomNode>> = HashMap::new();
+    for mut child in usage.children {
+        let slot = child.attributes.remove("slot").unwrap_or_default();
+        slots.entry(slot).or_default().push(child);
+    }
+
+    fill_slots(&mut expanded, &mut slots);
+
+    for (name, nodes) in slots {
+        let message = if name.is_empty() {
+            format!("el componente <{}> no tiene <slot> por defecto", usage.tag_name)
+        } else {
+            format!("el componente <{}> no tiene un slot '{}'", usage.tag_name, name)
+        };
+        diagnostics.warning(nodes.first().and_then(|node| node.span), message);
+    }
+
+    expanded
+}
+
+// Reemplaza cada <slot> por el contenido asignado o, si no hay, por su contenido por defecto
+fn fill_slots(node: &mut DomNode, slots: &mut HashMap<String, Vec<DomNode>>) {
+    let mut children = Vec::with_capacity(node.children.len());
+
+    for mut child in std::mem::take(&mut node.children) {
+        if child.tag_name == "slot" {
+            let name = child.attributes.get("name").cloned().unwrap_or_default();
+            match slots.remove(&name) {
+                Some(content) => children.extend(content),
+                None => children.extend(child.children),
+            }
+        } else {
+            fill_slots(&mut child, slots);
+            children.push(child);
+        }
+    }
+
+    node.children = children;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::html_parser::{parse_html, DomNode, ParseOptions};
+
+    fn find<'a>(node: &'a DomNode, tag: &str) -> Option<&'a DomNode> {
+        if node.tag_name == tag {
+            return Some(node);
+        }
+        node.children.iter().find_map(|child| find(child, tag))
+    }
+
+    #[test]
+    fn handler_placeholders_are_not_props() {
+        let source = r#"
+            <template name="field">
+                <form onsubmit="exec:save {$title} {#note}">
+                    <input name="title" placeholder="{label}" onchange="exec:notify-send {$value}"/>
+                </form>
+            </template>
+            <widget id="w"><body><field label="Título"/></body></widget>
+        "#;
+        let output = parse_html(source, &ParseOptions::default()).unwrap();
+        let body = &output.widgets[0].body;
+
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+        let form = find(body, "form").unwrap();
+        assert_eq!(form.attributes["onsubmit"], "exec:save {$title} {#note}");
+        let input = find(body, "input").unwrap();
+        assert_eq!(input.attributes["placeholder"], "Título");
+        assert_eq!(input.attributes["onchange"], "exec:notify-send {$value}");
+    }
+}
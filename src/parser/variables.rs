@@ -0,0 +1,195 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::diagnostics::{Diagnostics, Span};
+use super::html_parser::{tidy_text, DomNode};
+use super::permissions::{Denied, Permissions};
+
+/// Origen de los datos declarados con `<data name="...">`.
+#[derive(Debug, Clone)]
+pub enum DataSource {
+    /// JSON escrito dentro de la etiqueta.
+    Inline(Value),
+    /// Archivo JSON (`src`), relativo al archivo del widget; se vuelve a leer
+    /// en cada evaluación.
+    File(PathBuf),
+}
+
+/// Variables y datos de un widget, necesarios para evaluar su body.
+///
+/// Un nombre se busca primero en `overrides`, luego en `sources` y en
+/// `variables`. Las variables de entorno solo llegan a `variables` si una
+/// `<var env="...">` las pide.
+#[derive(Debug, Clone, Default)]
+pub struct WidgetData {
+    pub file: Option<PathBuf>,
+    pub variables: HashMap<String, String>,
+    /// Variables de línea de comandos, que tapan a las `<var>` y `<data>`.
+    pub overrides: HashMap<String, String>,
+    pub sources: HashMap<String, DataSource>,
+    /// Permisos concedidos; los `<data src>` fuera de la carpeta del widget los necesitan.
+    pub permissions: Permissions,
+    /// Permisos declarados que el usuario aún no ha aprobado. Lo que solo
+    /// ellos permiten no se lee ni se avisa: se lee al aprobarlos.
+    pub requested: Permissions,
+    /// Las `<var>` y `<data>` tal y como se escribieron, sin los valores de
+    /// línea de comandos, para poder volver a escribir el widget.
+    pub declarations: Vec<DomNode>,
+}
+
+impl WidgetData {
+    /// Concede los permisos declarados si ya están aprobados (`trusted`); si
+    /// no, quedan pendientes en `requested`.
+    pub(crate) fn grant(&mut self, permissions: &Permissions, trusted: bool) {
+        let (granted, requested) = match trusted {
+            true => (permissions.clone(), Permissions::default()),
+            false => (Permissions::default(), permissions.clone()),
+        };
+        self.permissions = granted;
+        self.requested = requested;
+    }
+
+    /// Si el widget puede leer `path`. `Ok(false)` es que solo se lo permiten
+    /// los permisos pendientes: no se lee todavía, pero tampoco es un error.
+    pub(crate) fn check_read(&self, path: &Path) -> Result<bool, Denied> {
+        let file = self.file.as_deref();
+        match self.permissions.check_read(path, file) {
+            Ok(()) => Ok(true),
+            Err(_) if self.requested.check_read(path, file).is_ok() => Ok(false),
+            Err(denied) => Err(denied),
+        }
+    }
+
+    /// Archivos de datos de los que depende el widget.
+    pub fn data_files(&self) -> Vec<PathBuf> {
+        self.sources
+            .values()
+            .filter_map(|source| match source {
+                DataSource::File(path) => Some(path.clone()),
+                DataSource::Inline(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Extrae las declaraciones `<var>` y `<data>` del árbol.
+pub(crate) fn collect_declarations(
+    node: &mut DomNode,
+    data: &mut WidgetData,
+    diagnostics: &mut Diagnostics,
+) {
+    collect_in(node, false, data, diagnostics);
+}
+
+fn collect_in(node: &mut DomNode, preformatted: bool, data: &mut WidgetData, diagnostics: &mut Diagnostics) {
+    let preformatted = preformatted || node.tag_name == "pre";
+    let mut children = Vec::with_capacity(node.children.len());
+    let mut removed = false;
+
+    for mut child in std::mem::take(&mut node.children) {
+        if is_declaration(&child) {
+            declare(&child, data, diagnostics);
+            removed = true;
+        } else {
+            collect_in(&mut child, preformatted, data, diagnostics);
+            children.push(child);
+        }
+    }
+
+    node.children = children;
+    // Los espacios que rodeaban la declaración quedan juntos o en un extremo
+    if removed {
+        tidy_text(node, preformatted);
+    }
+}
+
+pub(crate) fn is_declaration(node: &DomNode) -> bool {
+    node.tag_name == "var" || node.tag_name == "data"
+}
+
+/// Registra una declaración `<var name="" value=""/>` o `<data name="">`.
+/// `<var name="" env="NOMBRE"/>` toma el valor de esa variable de entorno, o
+/// `value` si no existe.
+pub(crate) fn declare(node: &DomNode, data: &mut WidgetData, diagnostics: &mut Diagnostics) {
+    let Some(name) = node.attributes.get("name").map(|name| name.trim().to_string()) else {
+        diagnostics.warning(node.span, format!("<{}> sin atributo 'name' se ignora", node.tag_name));
+        return;
+    };
+
+    if node.tag_name == "var" {
+        let value = node
+            .attributes
+            .get("env")
+            .and_then(|env| std::env::var(env.trim()).ok())
+            .or_else(|| node.attributes.get("value").cloned())
+            .unwrap_or_default();
+        data.variables.insert(name, value);
+        data.declarations.push(node.clone());
+        return;
+    }
+
+    if let Some(src) = node.attributes.get("src") {
+        let base_dir = data.file.as_deref().and_then(Path::parent).unwrap_or(Path::new(""));
+        data.sources.insert(name, DataSource::File(base_dir.join(src)));
+        data.declarations.push(node.clone());
+        return;
+    }
+
+    let inline: String = node
+        .children
+        .iter()
+        .filter_map(|child| child.text_content.as_deref())
+        .collect();
+
+    match serde_json::from_str(&inline) {
+        Ok(value) => {
+            data.sources.insert(name, DataSource::Inline(value));
+            data.declarations.push(node.clone());
+        }
+        Err(e) => diagnostics.warning(node.span, format!("JSON inválido en <data name=\"{}\">: {}", name, e)),
+    }
+}
+
+/// Resuelve los `{{ nombre }}` de un texto. Las variables que no existen se
+/// avisan y se sustituyen por una cadena vacía; `\{{` deja `{{` sin evaluar.
+pub(crate) fn interpolate_str(
+    input: &str,
+    span: Option<Span>,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    diagnostics: &mut Diagnostics,
+) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(open) = rest.find("{{") {
+        // `\{{` escribe unas llaves literales
+        if rest[..open].ends_with('\\') {
+            output.push_str(&rest[..open - 1]);
+            output.push_str("{{");
+            rest = &rest[open + 2..];
+            continue;
+        }
+
+        output.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+
+        let Some(close) = after.find("}}") else {
+            diagnostics.warning(span, format!("falta '}}}}' en \"{}\"", input));
+            output.push_str(&rest[open..]);
+            rest = "";
+            break;
+        };
+
+        let name = after[..close].trim();
+        match lookup(name) {
+            Some(value) => output.push_str(&value),
+            None => diagnostics.warning(span, format!("variable desconocida '{}'", name)),
+        }
+
+        rest = &after[close + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
@@ -1,8 +1,9 @@
 use gtk4::ApplicationWindow;
 
+use crate::parser::html_parser::WindowConfig;
+
 #[cfg(target_os="linux")]
-pub fn set_as_desktop_widget(
-    window: &ApplicationWindow, width: i32, height: i32, x: Option<i32>, y: Option<i32>) {
+pub fn set_as_desktop_widget(window: &ApplicationWindow, config: &WindowConfig) {
     //TODO Make the compatibility for linux "use the layer library from wayland"
 
     //window.set_decorated(false);
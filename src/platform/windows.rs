@@ -1,3 +1,5 @@
+use crate::parser::geometry::{window_geometry, Rect};
+use crate::parser::html_parser::WindowConfig;
 use crate::vprintln;
 use gtk4::prelude::{NativeExt, ObjectType, WidgetExt};
 use gtk4::ApplicationWindow;
@@ -9,13 +11,7 @@ use windows::Win32::UI::WindowsAndMessaging::{
     GetWindowLongPtrW, SetWindowLongPtrW, SetWindowPos, GWL_EXSTYLE, HWND_BOTTOM, SWP_NOACTIVATE,
     SWP_SHOWWINDOW, WS_EX_NOACTIVATE, WS_EX_TOOLWINDOW,
 };
-pub fn set_as_desktop_widget(
-    window: &ApplicationWindow,
-    window_width: i32,
-    window_height: i32,
-    x_pos: Option<i32>,
-    y_pos: Option<i32>,
-) {
+pub fn set_as_desktop_widget(window: &ApplicationWindow, config: &WindowConfig) {
     gtk4::prelude::WidgetExt::realize(window);
     if let Some(surface) = window.surface() {
         unsafe {
@@ -29,16 +25,18 @@ pub fn set_as_desktop_widget(
                 };
                 GetMonitorInfoW(hmonitor, &mut monitor_info);
 
-                let screen_width = monitor_info.rcWork.right - monitor_info.rcWork.left;
-                let screen_height = monitor_info.rcWork.bottom - monitor_info.rcWork.top;
-                
-                // I do this?
-                let x = x_pos.unwrap_or((screen_width - window_width) / 2);
-                let y = y_pos.unwrap_or((screen_height - window_height) / 2);
+                let work = monitor_info.rcWork;
+                let workarea = Rect {
+                    x: work.left,
+                    y: work.top,
+                    width: work.right - work.left,
+                    height: work.bottom - work.top,
+                };
+                let geometry = window_geometry(config, workarea);
 
-                vprintln!("Pantalla: {}x{}", screen_width, screen_height);
-                vprintln!("Ventana: {}x{}", window_width, window_height);
-                vprintln!("Posición: ({}, {})", x, y);
+                vprintln!("Área de trabajo: {}x{}", workarea.width, workarea.height);
+                vprintln!("Ventana: {}x{}", geometry.width, geometry.height);
+                vprintln!("Posición: ({}, {})", geometry.x, geometry.y);
                 let ex_style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
                 SetWindowLongPtrW(
                     hwnd,
@@ -48,10 +46,10 @@ pub fn set_as_desktop_widget(
                 let _ = SetWindowPos(
                     hwnd,
                     HWND_BOTTOM,
-                    x,
-                    y,
-                    window_width,
-                    window_height,
+                    geometry.x,
+                    geometry.y,
+                    geometry.width,
+                    geometry.height,
                     SWP_NOACTIVATE | SWP_SHOWWINDOW,
                 );
                 vprintln!("Ventana configurada como desktop widget!");
@@ -0,0 +1,64 @@
+use gtk4::prelude::*;
+use gtk4::Widget;
+use std::path::{Path, PathBuf};
+
+use crate::parser::permissions::{Denied, Permissions};
+
+/// Clave con la que cada ventana guarda los permisos de su widget.
+const ACCESS_KEY: &str = "ytml-access";
+
+/// Lo que se le permite hacer al widget de una ventana.
+#[derive(Debug, Clone, Default)]
+pub struct Access {
+    pub widget: String,
+    pub permissions: Permissions,
+    /// Archivo del widget. Su carpeta propia siempre se puede leer, ver
+    /// `Permissions`, y lo relativo parte de su directorio.
+    pub file: Option<PathBuf>,
+}
+
+impl Access {
+    /// Directorio del archivo del widget, del que parten las rutas relativas.
+    pub fn dir(&self) -> Option<PathBuf> {
+        self.file.as_deref().and_then(Path::parent).map(Path::to_path_buf)
+    }
+
+    /// Comprueba algo que quiere hacer el widget. Si no está permitido lo
+    /// avisa y devuelve `false`.
+    pub fn check(&self, check: impl FnOnce(&Permissions, Option<&Path>) -> Result<(), Denied>) -> bool {
+        match check(&self.permissions, self.file.as_deref()) {
+            Ok(()) => true,
+            Err(denied) => {
+                eprintln!("✗ Permiso denegado al widget '{}': {}", self.widget, denied);
+                false
+            }
+        }
+    }
+}
+
+/// Concede a los elementos de `window` lo que dice `access`. Una ventana sin
+/// permisos registrados no puede ejecutar órdenes ni abrir nada.
+pub fn grant_access(window: &impl IsA<gtk4::Window>, access: Access) {
+    // SAFETY: la clave solo se usa con `Access`, ver `access_of`
+    unsafe { window.set_data(ACCESS_KEY, access) };
+}
+
+/// Comprueba algo que quiere hacer `widget` con los permisos de su ventana.
+/// Si no está permitido lo avisa y devuelve `false`.
+pub fn check_access(widget: &Widget, check: impl FnOnce(&Permissions, Option<&Path>) -> Result<(), Denied>) -> bool {
+    access_of(widget).check(check)
+}
+
+/// Directorio del archivo del widget de la ventana de `widget`.
+pub fn widget_dir(widget: &Widget) -> Option<PathBuf> {
+    access_of(widget).dir()
+}
+
+// Sin permisos registrados no se concede nada
+fn access_of(widget: &Widget) -> Access {
+    // SAFETY: solo `grant_access` escribe esta clave, y siempre un `Access`
+    widget
+        .root()
+        .and_then(|root| unsafe { root.data::<Access>(ACCESS_KEY).map(|access| access.as_ref().clone()) })
+        .unwrap_or_default()
+}
@@ -0,0 +1,510 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib, Button, Editable, Label, TextView, Widget, Window};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::access::check_access;
+use super::commands::{run_command, Command, DEFAULT_TIMEOUT};
+use super::form::FormData;
+use super::inputs::{find_descendant, input_placeholder, replace_placeholders};
+use crate::parser::diagnostics::Diagnostics;
+use crate::parser::directives::{handler_values_attribute, EVENT_HANDLERS};
+use crate::parser::html_parser::DomNode;
+use crate::parser::variables::interpolate_str;
+
+/// Nombres de las acciones, para los mensajes de error.
+const ACTION_NAMES: &[&str] = &["print", "exec", "open", "toggle", "show", "hide", "set", "copy", "quit", "close"];
+
+/// Una acción de un manejador de eventos (`onclick`, `onchange`, `onsubmit`).
+/// Los textos admiten `{#id}` y `{$value}`, y en `onsubmit` `{$name}` con los
+/// campos del formulario; se sustituyen al ejecutarla. Los `{{ }}` de la
+/// plantilla llegan como `{%n}` y cada uno es un solo argumento.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// `print:texto`: escribe el texto en la salida estándar.
+    Print(String),
+    /// `exec(opciones):orden args...`: ejecuta la orden sin shell, con las
+    /// comillas de sh. Con la opción `shell` es un script para `sh -c`.
+    Exec { command: Command, shell: bool },
+    /// `open:uri`: abre una URI o un archivo con la aplicación por defecto.
+    Open(String),
+    /// `toggle:#id`: muestra el elemento si está oculto y al revés.
+    Toggle(String),
+    /// `show:#id`.
+    Show(String),
+    /// `hide:#id`.
+    Hide(String),
+    /// `set:#id.text=texto`: cambia el texto del elemento.
+    SetText { id: String, text: String },
+    /// `copy:texto`: copia el texto al portapapeles.
+    Copy(String),
+    /// `quit`: cierra la aplicación con todos sus widgets.
+    Quit,
+    /// `close`: cierra la ventana de este widget.
+    Close,
+}
+
+/// Acciones de un manejador separadas por `;`, en el orden en que se ejecutan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Actions {
+    actions: Vec<Action>,
+    /// Valores de los `{%n}`, los `{{ }}` del manejador en la plantilla.
+    values: Vec<String>,
+}
+
+impl FromStr for Actions {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, String> {
+        let actions = split_actions(source)
+            .iter()
+            .map(|action| action.parse())
+            .collect::<Result<Vec<Action>, String>>()?;
+
+        if actions.is_empty() {
+            return Err("no hay ninguna acción".to_string());
+        }
+        Ok(Actions { actions, values: Vec::new() })
+    }
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, String> {
+        let (name, argument) = match source.split_once(':') {
+            Some((name, argument)) => (name.trim(), Some(argument.trim())),
+            None => (source.trim(), None),
+        };
+        let (name, options) = match name.split_once('(') {
+            Some((name, options)) => {
+                let options = options
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("falta cerrar el paréntesis en \"{}\"", source.trim()))?;
+                (name.trim(), Some(options))
+            }
+            None => (name, None),
+        };
+
+        match (name, options, argument) {
+            ("exec", Some(options), Some(command)) if !command.is_empty() => exec_action(command, options),
+            (_, Some(_), _) if name != "exec" => Err(format!("\"{}\" no admite opciones", name)),
+            _ => simple_action(name, argument),
+        }
+    }
+}
+
+// `exec(timeout=5s, output=nombre, shell):...`
+fn exec_action(command: &str, options: &str) -> Result<Action, String> {
+    let mut shell = false;
+    let mut timeout = DEFAULT_TIMEOUT;
+    let mut output = None;
+
+    for option in options.split(',').map(str::trim).filter(|option| !option.is_empty()) {
+        match option.split_once('=').map(|(name, value)| (name.trim(), value.trim())) {
+            None if option == "shell" => shell = true,
+            Some(("timeout", value)) => timeout = parse_duration(value)?,
+            Some(("output", value)) if !value.is_empty() => output = Some(value.to_string()),
+            _ => {
+                return Err(format!(
+                    "opción de exec desconocida \"{}\" (timeout=, output= o shell)",
+                    option
+                ))
+            }
+        }
+    }
+
+    let mut command = if shell { Command::shell(command) } else { Command::new(parse_argv(command)?) };
+    command.timeout = timeout;
+    command.output = output;
+    Ok(Action::Exec { command, shell })
+}
+
+// `500ms`, `5s`, `2m` o un número de segundos
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let seconds = match (number.trim().parse::<f64>(), unit) {
+        (Ok(number), "ms") => number / 1000.0,
+        (Ok(number), "s") => number,
+        (Ok(number), "m") => number * 60.0,
+        _ => f64::NAN,
+    };
+
+    if seconds.is_finite() && seconds > 0.0 {
+        Ok(Duration::from_secs_f64(seconds))
+    } else {
+        Err(format!("timeout debe ser un tiempo positivo como 500ms, 5s o 2m, no \"{}\"", value))
+    }
+}
+
+fn parse_argv(command: &str) -> Result<Vec<String>, String> {
+    let argv = glib::shell_parse_argv(command).map_err(|error| format!("exec: {}", error))?;
+    Ok(argv.iter().map(|arg| arg.to_string_lossy().into_owned()).collect())
+}
+
+fn simple_action(name: &str, argument: Option<&str>) -> Result<Action, String> {
+    match (name, argument) {
+        ("quit", None) => Ok(Action::Quit),
+        ("close", None) => Ok(Action::Close),
+        ("quit" | "close", Some(_)) => Err(format!("\"{}\" no lleva argumento", name)),
+        (_, None | Some("")) if ACTION_NAMES.contains(&name) => {
+            Err(format!("falta el argumento de \"{}:\"", name))
+        }
+        ("print", Some(text)) => Ok(Action::Print(text.to_string())),
+        ("exec", Some(command)) => Ok(Action::Exec {
+            command: Command::new(parse_argv(command)?),
+            shell: false,
+        }),
+        ("open", Some(uri)) => Ok(Action::Open(uri.to_string())),
+        ("toggle", Some(target)) => Ok(Action::Toggle(element_id(name, target)?)),
+        ("show", Some(target)) => Ok(Action::Show(element_id(name, target)?)),
+        ("hide", Some(target)) => Ok(Action::Hide(element_id(name, target)?)),
+        ("set", Some(assignment)) => {
+            let (target, text) = assignment
+                .split_once('=')
+                .ok_or_else(|| format!("se esperaba \"set:#id.text=...\", no \"set:{}\"", assignment))?;
+            let (target, property) = target.trim().split_once('.').unwrap_or((target.trim(), ""));
+            if property != "text" {
+                return Err(format!("\"set:\" solo admite la propiedad text, no \"{}\"", property));
+            }
+            Ok(Action::SetText {
+                id: element_id(name, target)?,
+                text: text.trim().to_string(),
+            })
+        }
+        ("copy", Some(text)) => Ok(Action::Copy(text.to_string())),
+        _ => Err(format!(
+            "acción desconocida \"{}\" ({})",
+            name,
+            ACTION_NAMES.join(", ")
+        )),
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Action::Print(text) => write!(f, "print:{}", text),
+            Action::Exec { command, shell: true } => write!(f, "exec(shell):{}", command.argv[2]),
+            Action::Exec { command, .. } => {
+                let quoted: Vec<String> =
+                    command.argv.iter().map(|arg| glib::shell_quote(arg).to_string_lossy().into_owned()).collect();
+                write!(f, "exec:{}", quoted.join(" "))
+            }
+            Action::Open(uri) => write!(f, "open:{}", uri),
+            Action::Toggle(id) => write!(f, "toggle:#{}", id),
+            Action::Show(id) => write!(f, "show:#{}", id),
+            Action::Hide(id) => write!(f, "hide:#{}", id),
+            Action::SetText { id, text } => write!(f, "set:#{}.text={}", id, text),
+            Action::Copy(text) => write!(f, "copy:{}", text),
+            Action::Quit => f.write_str("quit"),
+            Action::Close => f.write_str("close"),
+        }
+    }
+}
+
+/// Lee el manejador `name` del nodo. Si tiene un error de sintaxis no hace
+/// nada; `check_event_handlers` ya lo avisó al cargar el widget.
+pub fn event_handler(node: &DomNode, name: &str) -> Option<Actions> {
+    let mut actions: Actions = node.attributes.get(name)?.parse().ok()?;
+    if let Some(values) = node.attributes.get(&handler_values_attribute(name)) {
+        actions.values = serde_json::from_str(values).unwrap_or_default();
+    }
+    Some(actions)
+}
+
+/// Analiza los manejadores de `node` y sus hijos y avisa de los errores de
+/// sintaxis con su línea. Se llama una vez al cargar el widget con su plantilla.
+pub(crate) fn check_event_handlers(node: &DomNode, diagnostics: &mut Diagnostics) {
+    for name in EVENT_HANDLERS {
+        let Some(source) = node.attributes.get(*name).filter(|source| !source.trim().is_empty()) else {
+            continue;
+        };
+        // Los `{{ }}` se analizan como los `{%n}` que serán al evaluar
+        let source = interpolate_str(source, node.span, &|_| Some("{%0}".to_string()), &mut Diagnostics::new("", None));
+        if let Err(error) = source.parse::<Actions>() {
+            diagnostics.warning(node.span, format!("{} en <{}>: {}, se ignora", name, node.tag_name, error));
+        }
+    }
+
+    for child in &node.children {
+        check_event_handlers(child, diagnostics);
+    }
+}
+
+impl Actions {
+    /// Ejecuta las acciones en orden. `widget` es el elemento que lanzó el
+    /// evento; si una acción falla se avisa y se sigue con la siguiente.
+    pub fn run(&self, widget: &Widget) {
+        self.run_with(widget, None);
+    }
+
+    /// Ejecuta las acciones al enviar `form` con los valores de `data`. Las
+    /// órdenes reciben además los valores como JSON por la entrada estándar
+    /// y como variables `FORM_*`.
+    pub fn submit(&self, form: &Widget, data: &FormData) {
+        self.run_with(form, Some(data));
+    }
+
+    fn run_with(&self, widget: &Widget, form: Option<&FormData>) {
+        for action in &self.actions {
+            if let Err(error) = run_action(action, &self.values, widget, form) {
+                eprintln!("  ⚠ {}: {}", action, error);
+            }
+        }
+    }
+}
+
+fn run_action(action: &Action, values: &[String], widget: &Widget, form: Option<&FormData>) -> Result<(), String> {
+    let value = |key: &str| {
+        handler_value(key, values)
+            .or_else(|| form.and_then(|form| form.placeholder(key)))
+            .or_else(|| input_placeholder(key, widget))
+    };
+    let expand = |text: &str| replace_placeholders(text, |key, _| value(key));
+
+    match action {
+        Action::Print(text) => println!("{}", expand(text)),
+        Action::Exec { command, shell } => {
+            let mut command = command.clone();
+            command.argv = if *shell {
+                shell_argv(&command.argv, value)
+            } else {
+                command.argv.iter().map(|arg| expand(arg)).collect()
+            };
+            if let Some(form) = form {
+                command.stdin = Some(form.to_json().to_string());
+                command.env = form.env_vars();
+            }
+            run_command(command, widget);
+        }
+        Action::Open(target) => {
+            // Sin esquema es una ruta, relativa al directorio de trabajo
+            let file = gio::File::for_commandline_arg(expand(target));
+            let allowed = check_access(widget, |permissions, base| match file.path() {
+                Some(path) => permissions.check_read(&path, base),
+                None => permissions.check_uri(&file.uri(), base),
+            });
+            if allowed {
+                gio::AppInfo::launch_default_for_uri(&file.uri(), gio::AppLaunchContext::NONE)
+                    .map_err(|error| error.to_string())?;
+            }
+        }
+        Action::Toggle(id) => {
+            let target = find_element(widget, &expand(id))?;
+            target.set_visible(!target.is_visible());
+        }
+        Action::Show(id) => find_element(widget, &expand(id))?.set_visible(true),
+        Action::Hide(id) => find_element(widget, &expand(id))?.set_visible(false),
+        Action::SetText { id, text } => set_text(&find_element(widget, &expand(id))?, &expand(text))?,
+        Action::Copy(text) => widget.clipboard().set_text(&expand(text)),
+        Action::Quit => {
+            let application = window_of(widget)?.application().ok_or("el widget no tiene aplicación")?;
+            application.quit();
+        }
+        Action::Close => window_of(widget)?.close(),
+    }
+
+    Ok(())
+}
+
+// Un valor pegado al script lo volvería a leer el shell, con `$(...)` y
+// todo. Así que cada `{...}` pasa a ser un parámetro posicional, con las
+// comillas que tocan donde aparece, y el valor va aparte:
+// `sh -c 'notify "${1}"' sh valor`
+// `{%n}`: el valor `n` de los `{{ }}` del manejador
+fn handler_value(key: &str, values: &[String]) -> Option<String> {
+    values.get(key.strip_prefix('%')?.parse::<usize>().ok()?).cloned()
+}
+
+fn shell_argv(argv: &[String], value: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut values = Vec::new();
+    let script = replace_placeholders(&argv[2], |key, written| {
+        values.push(value(key)?);
+        let parameter = format!("${{{}}}", values.len());
+        Some(match open_quote(written) {
+            Quote::None => format!("\"{}\"", parameter),
+            Quote::Double => parameter,
+            Quote::Single => format!("'\"{}\"'", parameter),
+        })
+    });
+
+    // `$0` es el nombre del programa; los valores empiezan en `$1`
+    let program = argv[0].clone();
+    [program.clone(), argv[1].clone(), script, program].into_iter().chain(values).collect()
+}
+
+enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+// Comillas que quedan abiertas al final de `script`, como las lee sh
+fn open_quote(script: &str) -> Quote {
+    let mut quote = Quote::None;
+    let mut chars = script.chars();
+
+    while let Some(c) = chars.next() {
+        quote = match (quote, c) {
+            (Quote::Single, '\'') => Quote::None,
+            (Quote::Single, _) => Quote::Single,
+            (quote, '\\') => {
+                chars.next();
+                quote
+            }
+            (Quote::None, '\'') => Quote::Single,
+            (Quote::None, '"') => Quote::Double,
+            (Quote::Double, '"') => Quote::None,
+            (quote, _) => quote,
+        };
+    }
+
+    quote
+}
+
+// Etiquetas, botones y campos cambian su texto; un contenedor, el de su
+// primera etiqueta (`<p id="x">` es una caja con el texto dentro)
+fn set_text(target: &Widget, text: &str) -> Result<(), String> {
+    if let Some(label) = target.downcast_ref::<Label>() {
+        label.set_text(text);
+    } else if let Some(button) = target.downcast_ref::<Button>() {
+        button.set_label(text);
+    } else if let Some(editable) = target.dynamic_cast_ref::<Editable>() {
+        editable.set_text(text);
+    } else if let Some(text_view) = find_descendant(target, &|child| child.is::<TextView>()) {
+        text_view.downcast::<TextView>().map_err(|_| "no es un TextView")?.buffer().set_text(text);
+    } else if let Some(label) = find_descendant(target, &|child| child.is::<Label>()) {
+        label.downcast::<Label>().map_err(|_| "no es un Label")?.set_text(text);
+    } else {
+        return Err("el elemento no tiene texto".to_string());
+    }
+    Ok(())
+}
+
+fn find_element(widget: &Widget, id: &str) -> Result<Widget, String> {
+    let root = widget.root().ok_or("el widget no está en una ventana")?;
+    find_descendant(root.upcast_ref(), &|child| child.widget_name() == id)
+        .ok_or_else(|| format!("no hay ningún elemento con id \"{}\"", id))
+}
+
+fn window_of(widget: &Widget) -> Result<Window, String> {
+    widget
+        .root()
+        .and_downcast::<Window>()
+        .ok_or_else(|| "el widget no está en una ventana".to_string())
+}
+
+fn element_id(action: &str, target: &str) -> Result<String, String> {
+    match target.trim().strip_prefix('#') {
+        Some(id) if !id.is_empty() => Ok(id.to_string()),
+        _ => Err(format!("\"{}:\" espera un #id, no \"{}\"", action, target.trim())),
+    }
+}
+
+// `;` separa acciones; `\;` es un punto y coma dentro de una acción
+fn split_actions(source: &str) -> Vec<String> {
+    let mut actions = Vec::new();
+    let mut current = String::new();
+    let mut chars = source.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&';') => current.push(chars.next().unwrap_or(';')),
+            ';' => actions.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    actions.push(current);
+
+    // Un `;` al final o dos seguidos no añaden acciones vacías
+    actions.into_iter().filter(|action| !action.trim().is_empty()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(script: &str) -> Vec<String> {
+        let command = Command::shell(script);
+        shell_argv(&command.argv, |key| (key == "$value").then(|| "$(rm -rf ~)".to_string()))
+    }
+
+    #[test]
+    fn shell_values_are_positional_parameters() {
+        assert_eq!(argv("notify {$value}"), ["sh", "-c", "notify \"${1}\"", "sh", "$(rm -rf ~)"]);
+    }
+
+    #[test]
+    fn shell_parameters_follow_the_surrounding_quotes() {
+        assert_eq!(argv("notify \"Hi {$value}\"")[2], "notify \"Hi ${1}\"");
+        assert_eq!(argv("notify 'Hi {$value}'")[2], "notify 'Hi '\"${1}\"''");
+        assert_eq!(argv("notify \"it's\" {$value}")[2], "notify \"it's\" \"${1}\"");
+    }
+
+    #[test]
+    fn syntax_errors_are_reported_at_load_with_their_line() {
+        use crate::parser::diagnostics::Span;
+
+        let button = |onclick: &str, line: usize| DomNode {
+            tag_name: "button".to_string(),
+            attributes: [("onclick".to_string(), onclick.to_string())].into(),
+            span: Some(Span { line, column: 5 }),
+            ..Default::default()
+        };
+        let body = DomNode {
+            tag_name: "body".to_string(),
+            children: vec![
+                button("toggle:#menu; quit", 2),
+                button("toggle:menu", 3),
+                button("", 4),
+                button("exec:cat {{ file }}; toggle:#{{ id }}", 5),
+            ],
+            ..Default::default()
+        };
+
+        let mut diagnostics = Diagnostics::new("", None);
+        check_event_handlers(&body, &mut diagnostics);
+        let warnings = diagnostics.into_warnings();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].span.map(|span| span.line), Some(3));
+        assert_eq!(warnings[0].message, "onclick en <button>: \"toggle:\" espera un #id, no \"menu\", se ignora");
+    }
+
+    #[test]
+    fn template_values_are_single_arguments() {
+        let node = DomNode {
+            tag_name: "button".to_string(),
+            attributes: [
+                ("onclick".to_string(), "exec:cat {%0}; print:{%1}".to_string()),
+                (handler_values_attribute("onclick"), r#"["mis notas.txt; quit","a b"]"#.to_string()),
+            ]
+            .into(),
+            ..Default::default()
+        };
+        let actions = event_handler(&node, "onclick").unwrap();
+        assert_eq!(actions.actions.len(), 2);
+
+        let Action::Exec { command, .. } = &actions.actions[0] else { panic!("se esperaba exec") };
+        let argv: Vec<String> = command
+            .argv
+            .iter()
+            .map(|arg| replace_placeholders(arg, |key, _| handler_value(key, &actions.values)))
+            .collect();
+        assert_eq!(argv, ["cat", "mis notas.txt; quit"]);
+
+        let script = Command::shell("notify {%0}");
+        let shell = shell_argv(&script.argv, |key| handler_value(key, &actions.values));
+        assert_eq!(shell, ["sh", "-c", "notify \"${1}\"", "sh", "mis notas.txt; quit"]);
+    }
+
+    #[test]
+    fn unknown_placeholders_stay_in_the_script() {
+        assert_eq!(argv("echo {#missing} {$value}"), ["sh", "-c", "echo {#missing} \"${1}\"", "sh", "$(rm -rf ~)"]);
+    }
+}
@@ -0,0 +1,241 @@
+use gtk4::prelude::*;
+use gtk4::{Align, Widget};
+
+use super::sized::{AxisSize, SizedBin};
+use crate::parser::geometry::{Length, Margins};
+use crate::parser::html_parser::DomNode;
+
+/// Atributos de tamaño y colocación que admiten todos los elementos:
+/// `width`, `height`, `min-width` y `max-width` en píxeles o porcentaje del
+/// padre, `hexpand`/`vexpand`, `halign`/`valign` y `margin`/`padding` como
+/// en CSS (de 1 a 4 valores).
+#[derive(Debug, Default)]
+pub struct BoxAttributes {
+    width: Option<Length>,
+    height: Option<Length>,
+    min_width: Option<Length>,
+    max_width: Option<Length>,
+    hexpand: Option<bool>,
+    vexpand: Option<bool>,
+    halign: Option<Align>,
+    valign: Option<Align>,
+    margin: Option<Margins>,
+    padding: Option<Margins>,
+}
+
+impl BoxAttributes {
+    /// Lee los atributos del nodo. Los valores inválidos se ignoran;
+    /// `check_layout` ya los avisó al cargar el widget.
+    pub fn from_node(node: &DomNode) -> Self {
+        BoxAttributes {
+            width: attribute(node, "width", parse_length),
+            height: attribute(node, "height", parse_length),
+            min_width: attribute(node, "min-width", parse_length),
+            max_width: attribute(node, "max-width", parse_length),
+            hexpand: attribute(node, "hexpand", parse_bool),
+            vexpand: attribute(node, "vexpand", parse_bool),
+            halign: attribute(node, "halign", parse_align),
+            valign: attribute(node, "valign", parse_align),
+            margin: attribute(node, "margin", parse_margins),
+            padding: attribute(node, "padding", parse_padding),
+        }
+    }
+
+    /// Aplica los atributos al widget. Con porcentajes o `max-width` el
+    /// widget queda dentro de un `SizedBin`, que es lo que se devuelve.
+    pub fn apply(&self, widget: Widget) -> Widget {
+        if let Some(hexpand) = self.hexpand {
+            widget.set_hexpand(hexpand);
+        }
+        if let Some(vexpand) = self.vexpand {
+            widget.set_vexpand(vexpand);
+        }
+        if let Some(halign) = self.halign {
+            widget.set_halign(halign);
+        }
+        if let Some(valign) = self.valign {
+            widget.set_valign(valign);
+        }
+        if let Some(padding) = self.padding {
+            widget.add_css_class(&padding_class(&padding));
+        }
+
+        let width = AxisSize {
+            size: self.width,
+            min: self.min_width,
+            max: self.max_width,
+        };
+        let height = AxisSize {
+            size: self.height,
+            ..AxisSize::default()
+        };
+
+        // Los píxeles son un tamaño mínimo de GTK; el resto lo resuelve el `SizedBin`
+        if let Some(px) = px_request(&width) {
+            widget.set_width_request(px);
+        }
+        if let Some(px) = px_request(&height) {
+            widget.set_height_request(px);
+        }
+
+        let widget = if width.needs_bin() || height.needs_bin() {
+            SizedBin::new(&widget, width, height).upcast()
+        } else {
+            widget
+        };
+
+        if let Some(margin) = self.margin {
+            widget.set_margin_top(margin.top);
+            widget.set_margin_end(margin.right);
+            widget.set_margin_bottom(margin.bottom);
+            widget.set_margin_start(margin.left);
+        }
+
+        widget
+    }
+}
+
+/// Posición de un hijo con `position="absolute"`: `left` y `top` en píxeles
+/// desde la esquina superior izquierda de su contenedor.
+pub fn absolute_position(node: &DomNode) -> Option<(i32, i32)> {
+    if !attribute(node, "position", parse_position)? {
+        return None;
+    }
+
+    let left = attribute(node, "left", parse_offset).unwrap_or(0);
+    let top = attribute(node, "top", parse_offset).unwrap_or(0);
+    Some((left, top))
+}
+
+/// Desplazamiento de un hijo de `<overlay>` (`offset-x`, `offset-y`) desde
+/// donde lo colocan `halign` y `valign`.
+pub fn overlay_offset(node: &DomNode) -> (i32, i32) {
+    (
+        attribute(node, "offset-x", parse_offset).unwrap_or(0),
+        attribute(node, "offset-y", parse_offset).unwrap_or(0),
+    )
+}
+
+/// Atributo numérico (`min`, `max`, `step`...), si es un número.
+pub fn number_attribute(node: &DomNode, name: &str) -> Option<f64> {
+    attribute(node, name, parse_number)
+}
+
+/// Atributo booleano de HTML (`disabled`, `checked`...): basta con que
+/// esté, salvo que valga `"false"`.
+pub fn flag(node: &DomNode, name: &str) -> bool {
+    node.attributes.get(name).is_some_and(|value| value.trim() != "false")
+}
+
+/// Clase CSS del atributo `padding`. GTK solo admite relleno desde CSS, así
+/// que el `ScopedStyle` de la ventana carga una regla por cada clase.
+pub fn padding_class(padding: &Margins) -> String {
+    format!(
+        "ytml-padding-{}-{}-{}-{}",
+        padding.top, padding.right, padding.bottom, padding.left
+    )
+}
+
+fn px_request(axis: &AxisSize) -> Option<i32> {
+    [axis.size, axis.min]
+        .into_iter()
+        .filter_map(|length| match length? {
+            px @ Length::Px(_) => Some(px.resolve(0)),
+            Length::Percent(_) => None,
+        })
+        .max()
+}
+
+/// Lee `name` con `parse`; si no es válido lo ignora.
+pub fn attribute<T>(node: &DomNode, name: &str, parse: fn(&str) -> Result<T, String>) -> Option<T> {
+    parse(node.attributes.get(name)?.trim()).ok()
+}
+
+/// Error del atributo `name` de `node` si `BoxAttributes`, la posición o los
+/// números de un `<input>` no lo podrán leer.
+pub(crate) fn invalid_attribute(node: &DomNode, name: &str, value: &str) -> Option<String> {
+    match name {
+        "width" | "height" | "min-width" | "max-width" => parse_length(value).err(),
+        "hexpand" | "vexpand" => parse_bool(value).err(),
+        "halign" | "valign" => parse_align(value).err(),
+        "margin" => parse_margins(value).err(),
+        "padding" => parse_padding(value).err(),
+        "position" => parse_position(value).err(),
+        "left" | "top" | "offset-x" | "offset-y" => parse_offset(value).err(),
+        "min" | "max" | "step" | "value" if is_number_input(node) => parse_number(value).err(),
+        _ => None,
+    }
+}
+
+// Los que leen `number_attribute`
+fn is_number_input(node: &DomNode) -> bool {
+    node.tag_name == "range"
+        || node.tag_name == "input" && matches!(node.attributes.get("type").map(|kind| kind.trim()), Some("number" | "range"))
+}
+
+fn parse_length(value: &str) -> Result<Length, String> {
+    value.parse()
+}
+
+// `<div hexpand>` sin valor también cuenta como `true`
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "" | "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("se esperaba \"true\" o \"false\", no \"{}\"", value)),
+    }
+}
+
+fn parse_align(value: &str) -> Result<Align, String> {
+    match value {
+        "fill" | "stretch" => Ok(Align::Fill),
+        "start" => Ok(Align::Start),
+        "center" => Ok(Align::Center),
+        "end" => Ok(Align::End),
+        "baseline" => Ok(Align::BaselineFill),
+        _ => Err(format!(
+            "alineación desconocida \"{}\" (fill, start, center, end o baseline)",
+            value
+        )),
+    }
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| format!("se esperaba un número, no \"{}\"", value))
+}
+
+fn parse_position(value: &str) -> Result<bool, String> {
+    match value {
+        "absolute" => Ok(true),
+        "static" => Ok(false),
+        _ => Err(format!("se esperaba \"absolute\" o \"static\", no \"{}\"", value)),
+    }
+}
+
+// Puede ser negativo para sacar el hijo por arriba o por la izquierda
+fn parse_offset(value: &str) -> Result<i32, String> {
+    value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .trim()
+        .parse()
+        .map_err(|_| format!("se esperaban píxeles, no \"{}\"", value))
+}
+
+fn parse_margins(value: &str) -> Result<Margins, String> {
+    value
+        .parse()
+        .map_err(|_| format!("se esperaban de 1 a 4 valores en píxeles, no \"{}\"", value))
+}
+
+pub fn parse_padding(value: &str) -> Result<Margins, String> {
+    let padding = parse_margins(value)?;
+    if [padding.top, padding.right, padding.bottom, padding.left].iter().any(|side| *side < 0) {
+        return Err(format!("el relleno no puede ser negativo: \"{}\"", value));
+    }
+    Ok(padding)
+}
@@ -0,0 +1,386 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib, Widget};
+use serde_json::{json, Value};
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::io::Write;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use super::access::{check_access, widget_dir};
+
+/// Órdenes que pueden estar en marcha a la vez; las demás esperan turno.
+const MAX_RUNNING: usize = 4;
+
+/// Bytes que se guardan de stdout y de stderr de cada orden.
+pub const MAX_OUTPUT: usize = 256 * 1024;
+
+const READ_CHUNK: usize = 16 * 1024;
+
+/// Tiempo límite de una orden si no se indica otro.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Clave con la que cada ventana guarda a quién entregar las salidas capturadas.
+const OUTPUT_KEY: &str = "ytml-command-output";
+
+type OutputCallback = Rc<dyn Fn(&str, &CommandOutput)>;
+
+/// Orden lista para ejecutar.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    /// Programa y argumentos; no pasan por ningún shell.
+    pub argv: Vec<String>,
+    pub timeout: Duration,
+    /// Variable en la que se guarda la salida (ver `on_command_output`).
+    pub output: Option<String>,
+    pub stdin: Option<String>,
+    pub env: Vec<(String, String)>,
+    /// Directorio de trabajo; `run_command` pone el del widget.
+    pub cwd: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn new(argv: Vec<String>) -> Self {
+        Command {
+            argv,
+            timeout: DEFAULT_TIMEOUT,
+            output: None,
+            stdin: None,
+            env: Vec::new(),
+            cwd: None,
+        }
+    }
+
+    /// `sh -c script`. Solo para los widgets que lo piden explícitamente.
+    pub fn shell(script: &str) -> Self {
+        Command::new(vec!["sh".to_string(), "-c".to_string(), script.to_string()])
+    }
+}
+
+/// Lo que dejó una orden al terminar.
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// Código de salida; `None` si terminó por una señal o no llegó a arrancar.
+    pub status: Option<i32>,
+    pub timed_out: bool,
+}
+
+impl CommandOutput {
+    /// `{ "stdout", "stderr", "status", "timed_out" }`, para usarlo como `<data>`.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "stdout": self.stdout,
+            "stderr": self.stderr,
+            "status": self.status,
+            "timed_out": self.timed_out,
+        })
+    }
+
+    fn succeeded(&self) -> bool {
+        self.status == Some(0)
+    }
+}
+
+/// Registra quién recibe las salidas de las órdenes con `output` lanzadas
+/// desde `window`. Se llama con el nombre de la variable y la salida.
+pub fn on_command_output(window: &impl IsA<gtk4::Window>, callback: impl Fn(&str, &CommandOutput) + 'static) {
+    let callback: OutputCallback = Rc::new(callback);
+    // SAFETY: la clave solo se usa con `OutputCallback`, ver `deliver`
+    unsafe { window.set_data(OUTPUT_KEY, callback) };
+}
+
+thread_local! {
+    static RUNNING: Cell<usize> = const { Cell::new(0) };
+    static QUEUE: RefCell<VecDeque<(Command, glib::WeakRef<Widget>)>> = const { RefCell::new(VecDeque::new()) };
+}
+
+/// Ejecuta `command` sin bloquear el bucle de GTK. `widget` es el elemento
+/// que la lanzó; la salida va a su ventana si la orden tiene `output` y a la
+/// salida estándar si no. Pasado el tiempo límite se mata la orden con
+/// todos los procesos que haya abierto. Solo se ejecuta si el widget la
+/// declaró en `<permissions>`, y siempre en el directorio del archivo del widget.
+pub fn run_command(mut command: Command, widget: &Widget) {
+    if command.argv.is_empty() {
+        return;
+    }
+    if !check_access(widget, |permissions, base| permissions.check_exec(&command.argv, base)) {
+        log(&format!("denegado {}", describe(&command.argv)));
+        return;
+    }
+    command.cwd = widget_dir(widget);
+
+    // Se guarda la ventana: el elemento puede desaparecer si se vuelve a renderizar
+    let window = widget.root().map_or_else(|| widget.clone(), |root| root.upcast());
+
+    if RUNNING.get() >= MAX_RUNNING {
+        log(&format!("en cola  {}", describe(&command.argv)));
+        QUEUE.with_borrow_mut(|queue| queue.push_back((command, window.downgrade())));
+        return;
+    }
+
+    start(command, window.downgrade());
+}
+
+fn start(command: Command, window: glib::WeakRef<Widget>) {
+    RUNNING.set(RUNNING.get() + 1);
+
+    let mut flags = gio::SubprocessFlags::STDOUT_PIPE | gio::SubprocessFlags::STDERR_PIPE;
+    if command.stdin.is_some() {
+        flags |= gio::SubprocessFlags::STDIN_PIPE;
+    }
+
+    let launcher = gio::SubprocessLauncher::new(flags);
+    for (name, value) in &command.env {
+        launcher.setenv(name, value, true);
+    }
+    if let Some(cwd) = &command.cwd {
+        launcher.set_cwd(cwd);
+    }
+    // Cada orden abre su propio grupo de procesos para poder matarlo entero
+    #[cfg(unix)]
+    launcher.set_child_setup(|| {
+        // SAFETY: `setpgid` es seguro entre `fork` y `exec`
+        unsafe { libc::setpgid(0, 0) };
+    });
+
+    let args: Vec<&OsStr> = command.argv.iter().map(OsStr::new).collect();
+    let process = match launcher.spawn(&args) {
+        Ok(process) => process,
+        Err(error) => {
+            log(&format!("error    {}: {}", describe(&command.argv), error));
+            eprintln!("✗ No se pudo ejecutar {}: {}", command.argv[0], error);
+            finish();
+            return;
+        }
+    };
+
+    let pid = process.identifier().map(|pid| pid.to_string()).unwrap_or_default();
+    log(&format!("inicio   [{}] {}", pid, describe(&command.argv)));
+
+    let started = Instant::now();
+    let cancellable = gio::Cancellable::new();
+    let timed_out = Rc::new(Cell::new(false));
+
+    // Se cancela también la lectura por si algún proceso escapó del grupo y
+    // mantiene abierta la salida
+    let timer = glib::timeout_add_local_once(command.timeout, {
+        let process = process.clone();
+        let cancellable = cancellable.clone();
+        let timed_out = timed_out.clone();
+        move || {
+            timed_out.set(true);
+            kill_tree(&process);
+            cancellable.cancel();
+        }
+    });
+
+    if let Some((stdin, pipe)) = command.stdin.clone().zip(process.stdin_pipe()) {
+        pipe.clone()
+            .write_all_async(stdin.into_bytes(), glib::Priority::DEFAULT, Some(&cancellable), move |_| {
+                // Sin cerrarla la orden esperaría más entrada
+                pipe.close_async(glib::Priority::DEFAULT, gio::Cancellable::NONE, |_| {});
+            });
+    }
+
+    // stdout y stderr se leen a la vez para que ninguna se llene y bloquee
+    // la orden; cuando se cierran las dos se espera a que termine
+    let output = Rc::new(RefCell::new(CommandOutput::default()));
+    let open_pipes = Rc::new(Cell::new(2));
+    let program = command.argv[0].clone();
+    let pending = RefCell::new(Some((command, window, timer, pid)));
+    let pipe_closed: Rc<dyn Fn()> = Rc::new({
+        let process = process.clone();
+        let output = output.clone();
+        let timed_out = timed_out.clone();
+        move || {
+            open_pipes.set(open_pipes.get() - 1);
+            let Some((command, window, timer, pid)) = pending.take().filter(|_| open_pipes.get() == 0) else {
+                return;
+            };
+
+            let process_ref = process.clone();
+            let output = output.clone();
+            let timed_out = timed_out.clone();
+            process.wait_async(gio::Cancellable::NONE, move |_| {
+                // Si saltó, GTK ya quitó el temporizador
+                if !timed_out.get() {
+                    timer.remove();
+                }
+
+                let mut output = output.take();
+                output.timed_out = timed_out.get();
+                output.status = process_ref.has_exited().then(|| process_ref.exit_status());
+
+                log(&format!(
+                    "fin      [{}] {} en {} ms: {}, {} B de stdout, {} B de stderr",
+                    pid,
+                    describe(&command.argv),
+                    started.elapsed().as_millis(),
+                    status_text(&output, command.timeout),
+                    output.stdout.len(),
+                    output.stderr.len()
+                ));
+
+                deliver(&command, &output, &window);
+                finish();
+            });
+        }
+    });
+
+    let streams = [(process.stdout_pipe(), false), (process.stderr_pipe(), true)];
+    for (stream, is_stderr) in streams {
+        let Some(stream) = stream else {
+            pipe_closed();
+            continue;
+        };
+        let output = output.clone();
+        let pipe_closed = pipe_closed.clone();
+        let program = program.clone();
+        let timed_out = timed_out.clone();
+        read_capped(stream, cancellable.clone(), Vec::new(), false, move |captured, truncated, error| {
+            if truncated {
+                eprintln!(
+                    "  ⚠ {} escribió más de {} KiB en {}; el resto se descarta",
+                    program,
+                    MAX_OUTPUT / 1024,
+                    if is_stderr { "stderr" } else { "stdout" }
+                );
+            }
+
+            let mut output = output.borrow_mut();
+            // La salida de una orden no tiene por qué ser UTF-8
+            let text = String::from_utf8_lossy(&captured).into_owned();
+            if is_stderr {
+                output.stderr = text;
+            } else {
+                output.stdout = text;
+            }
+            // Tras el tiempo límite la lectura se cancela a propósito
+            if let Some(error) = error.filter(|_| !timed_out.get()) {
+                output.stderr.push_str(&error.to_string());
+            }
+            drop(output);
+            pipe_closed();
+        });
+    }
+}
+
+// Lee `stream` hasta el final guardando como mucho `MAX_OUTPUT` bytes; el
+// resto se lee y se descarta para que la orden no se quede bloqueada
+fn read_capped(
+    stream: gio::InputStream,
+    cancellable: gio::Cancellable,
+    mut captured: Vec<u8>,
+    truncated: bool,
+    done: impl FnOnce(Vec<u8>, bool, Option<glib::Error>) + 'static,
+) {
+    stream
+        .clone()
+        .read_bytes_async(READ_CHUNK, glib::Priority::DEFAULT, Some(&cancellable.clone()), move |result| {
+            match result {
+                Ok(chunk) if chunk.is_empty() => done(captured, truncated, None),
+                Ok(chunk) => {
+                    let room = MAX_OUTPUT.saturating_sub(captured.len());
+                    captured.extend_from_slice(&chunk[..chunk.len().min(room)]);
+                    read_capped(stream, cancellable, captured, truncated || chunk.len() > room, done);
+                }
+                Err(error) => done(captured, truncated, Some(error)),
+            }
+        });
+}
+
+// Deja sitio a la siguiente orden en cola, si la hay
+fn finish() {
+    RUNNING.set(RUNNING.get().saturating_sub(1));
+
+    if let Some((command, window)) = QUEUE.with_borrow_mut(VecDeque::pop_front) {
+        start(command, window);
+    }
+}
+
+fn deliver(command: &Command, output: &CommandOutput, window: &glib::WeakRef<Widget>) {
+    let program = &command.argv[0];
+    if !output.succeeded() {
+        eprintln!("✗ {}: {}", program, status_text(output, command.timeout));
+        if !output.stderr.trim().is_empty() {
+            eprintln!("  {}", output.stderr.trim_end());
+        }
+    }
+
+    let Some(name) = &command.output else {
+        print!("{}", output.stdout);
+        return;
+    };
+
+    // La ventana puede haberse cerrado mientras tanto
+    // SAFETY: solo `on_command_output` escribe esta clave, y siempre un `OutputCallback`
+    let callback = window
+        .upgrade()
+        .and_then(|window| unsafe { window.data::<OutputCallback>(OUTPUT_KEY).map(|callback| callback.as_ref().clone()) });
+    match callback {
+        Some(callback) => callback(name, output),
+        None => eprintln!("  ⚠ No hay ventana para guardar la salida de {} en \"{}\"", program, name),
+    }
+}
+
+fn status_text(output: &CommandOutput, timeout: Duration) -> String {
+    match output.status {
+        _ if output.timed_out => format!("superó el tiempo límite de {:?}, se detuvo", timeout),
+        Some(0) => "terminó bien".to_string(),
+        Some(status) => format!("terminó con código {}", status),
+        None if output.stderr.is_empty() => "terminó por una señal".to_string(),
+        None => "falló".to_string(),
+    }
+}
+
+
+#[cfg(unix)]
+fn kill_tree(process: &gio::Subprocess) {
+    if let Some(pid) = process.identifier().and_then(|pid| pid.parse::<i32>().ok()) {
+        // SAFETY: un pid negativo manda la señal a todo el grupo del proceso
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+    process.force_exit();
+}
+
+#[cfg(not(unix))]
+fn kill_tree(process: &gio::Subprocess) {
+    process.force_exit();
+}
+
+fn describe(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| glib::shell_quote(arg).to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Registro de todas las órdenes ejecutadas.
+pub fn log_path() -> PathBuf {
+    glib::user_cache_dir().join("ytml").join("commands.log")
+}
+
+// Cada ejecución queda en el registro; con `--verbose` también en pantalla
+fn log(line: &str) {
+    crate::vprintln!("$ {}", line);
+
+    let time = glib::DateTime::now_local()
+        .and_then(|now| now.format("%F %T"))
+        .map(|time| time.to_string())
+        .unwrap_or_default();
+
+    let path = log_path();
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| writeln!(file, "{} {}", time, line));
+
+    if let Err(error) = written {
+        crate::vprintln!("No se pudo escribir en {:?}: {}", path, error);
+    }
+}
@@ -0,0 +1,962 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::parser::html_parser::DomNode;
+
+/// Propiedades de CSS web que GTK no entiende. No se pasan al `CssProvider`:
+/// se copian al `style` de los elementos que seleccionan y el renderer las
+/// aplica como propiedades del widget.
+const LAYOUT_PROPERTIES: &[&str] = &[
+    "width",
+    "height",
+    "display",
+    "gap",
+    "row-gap",
+    "column-gap",
+    "flex-direction",
+    "flex-wrap",
+    "align-items",
+    "justify-content",
+    "flex-grow",
+    "flex-shrink",
+    "text-align",
+    "opacity",
+    "overflow",
+    "overflow-x",
+    "overflow-y",
+];
+
+/// Las que GTK sí admite: en reglas con pseudoclases (`:hover`), que no se
+/// pueden resolver al renderizar, se dejan para el `CssProvider`.
+const GTK_NATIVE_PROPERTIES: &[&str] = &["opacity"];
+
+/// Propiedades de diseño que heredan los hijos.
+const INHERITED_LAYOUT_PROPERTIES: &[&str] = &["text-align"];
+
+/// Reglas `@` que contienen reglas normales: con `scope` se limitan sus
+/// selectores como los de fuera.
+const GROUP_AT_RULES: &[&str] = &["@media", "@supports"];
+
+/// Las que no seleccionan elementos y se pueden copiar aunque haya `scope`.
+/// El resto (`@import`, `@define-color`...) afectaría a todas las ventanas.
+const UNSCOPED_AT_RULES: &[&str] = &["@keyframes"];
+
+/// Límite de `var()` anidadas, para no entrar en bucle con referencias circulares.
+const MAX_VAR_DEPTH: usize = 16;
+
+pub fn is_layout_property(name: &str) -> bool {
+    LAYOUT_PROPERTIES.contains(&name)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declaration {
+    pub name: String,
+    pub value: String,
+}
+
+impl Declaration {
+    fn is_custom(&self) -> bool {
+        self.name.starts_with("--")
+    }
+}
+
+/// Declaraciones de un bloque o de un atributo `style`, en orden.
+pub fn parse_declarations(block: &str) -> Vec<Declaration> {
+    let block = strip_comments(block);
+    let mut declarations = Vec::new();
+    let mut rest = block.as_str();
+
+    loop {
+        let end = find_outside_strings(rest, |c| c == ';').unwrap_or(rest.len());
+        if let Some(declaration) = parse_declaration(&rest[..end]) {
+            declarations.push(declaration);
+        }
+        if end == rest.len() {
+            break;
+        }
+        rest = &rest[end + 1..];
+    }
+
+    declarations
+}
+
+fn parse_declaration(text: &str) -> Option<Declaration> {
+    let (name, value) = text.split_once(':')?;
+    let name = name.trim();
+    let value = value.trim();
+    if name.is_empty() || value.is_empty() {
+        return None;
+    }
+
+    // Las propiedades personalizadas distinguen mayúsculas, las demás no
+    let name = if name.starts_with("--") {
+        name.to_string()
+    } else {
+        name.to_ascii_lowercase()
+    };
+
+    Some(Declaration {
+        name,
+        value: value.to_string(),
+    })
+}
+
+pub fn format_declarations<'a>(declarations: impl IntoIterator<Item = &'a Declaration>) -> String {
+    declarations
+        .into_iter()
+        .map(|declaration| format!("{}: {}", declaration.name, declaration.value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    selectors: Vec<String>,
+    declarations: Vec<Declaration>,
+}
+
+#[derive(Debug, Clone)]
+enum Item {
+    Rule(Rule),
+    /// `@media` o `@supports` con sus reglas.
+    Group { prelude: String, sheet: StyleSheet },
+    /// `@import`, `@define-color`, `@keyframes`... se copian tal cual.
+    AtRule(String),
+}
+
+/// Hoja de estilos con las reglas anidadas ya aplanadas.
+#[derive(Debug, Clone, Default)]
+pub struct StyleSheet {
+    items: Vec<Item>,
+}
+
+impl StyleSheet {
+    pub fn parse(css: &str) -> Self {
+        let css = strip_comments(css);
+        let mut items = Vec::new();
+        let mut rest = css.as_str();
+
+        while let Some(end) = find_outside_strings(rest, |c| c == '{' || c == ';') {
+            let prelude = rest[..end].trim();
+
+            if rest[end..].starts_with(';') {
+                if !prelude.is_empty() {
+                    items.push(Item::AtRule(format!("{};", prelude)));
+                }
+                rest = &rest[end + 1..];
+                continue;
+            }
+
+            let block_end = block_end(rest, end);
+            let body = block_content(rest, end, block_end);
+            if is_group_rule(prelude) {
+                items.push(Item::Group {
+                    prelude: prelude.to_string(),
+                    sheet: StyleSheet::parse(body),
+                });
+            } else if prelude.starts_with('@') {
+                items.push(Item::AtRule(format!("{} {{{}}}", prelude, body)));
+            } else {
+                parse_rule(nest_selectors(&[], prelude), body, &mut items);
+            }
+            rest = &rest[block_end..];
+        }
+
+        StyleSheet { items }
+    }
+
+    /// Añade a `variables` las propiedades personalizadas de la hoja. Si se
+    /// declaran varias veces, gana la última.
+    pub fn collect_variables(&self, variables: &mut Variables) {
+        for item in &self.items {
+            if let Item::Rule(rule) = item {
+                for declaration in rule.declarations.iter().filter(|d| d.is_custom()) {
+                    variables.0.insert(declaration.name.clone(), declaration.value.clone());
+                }
+            }
+        }
+    }
+
+    /// CSS que entiende GTK: sin anidación, con las `var()` sustituidas y sin
+    /// las propiedades de diseño. Con `scope`, cada selector se limita a la
+    /// ventana que tiene esa clase, también dentro de `@media`, y se quitan
+    /// las reglas `@` que no se pueden limitar.
+    pub fn to_gtk_css(&self, scope: Option<&str>, variables: &Variables) -> String {
+        let mut css = String::new();
+
+        for item in &self.items {
+            let rule = match item {
+                Item::Group { prelude, sheet } => {
+                    let inner = sheet.to_gtk_css(scope, variables);
+                    if !inner.is_empty() {
+                        let _ = writeln!(css, "{} {{ {} }}", prelude, inner.trim_end().replace('\n', " "));
+                    }
+                    continue;
+                }
+                Item::AtRule(text) => {
+                    if scope.is_none() || UNSCOPED_AT_RULES.iter().any(|name| at_rule_is(text, name)) {
+                        css.push_str(text);
+                        css.push('\n');
+                    }
+                    continue;
+                }
+                Item::Rule(rule) => rule,
+            };
+
+            let resolved = |filter: &dyn Fn(&Declaration) -> bool| -> Vec<Declaration> {
+                rule.declarations
+                    .iter()
+                    .filter(|declaration| !declaration.is_custom() && filter(declaration))
+                    .filter_map(|declaration| {
+                        Some(Declaration {
+                            name: declaration.name.clone(),
+                            value: variables.resolve(&declaration.value)?,
+                        })
+                    })
+                    .collect()
+            };
+
+            let declarations = resolved(&|declaration| !is_layout_property(&declaration.name));
+            write_rule(&mut css, &rule.selectors, &declarations, scope);
+
+            let dynamic: Vec<String> = rule
+                .selectors
+                .iter()
+                .filter(|selector| Selector::parse(selector).is_none())
+                .cloned()
+                .collect();
+            if !dynamic.is_empty() {
+                let native = resolved(&|declaration| GTK_NATIVE_PROPERTIES.contains(&declaration.name.as_str()));
+                write_rule(&mut css, &dynamic, &native, scope);
+            }
+        }
+
+        css
+    }
+
+    /// Declaraciones de todas las reglas, en orden de aparición.
+    pub fn declarations(&self) -> impl Iterator<Item = &Declaration> {
+        self.items.iter().flat_map(|item| match item {
+            Item::Rule(rule) => rule.declarations.as_slice(),
+            Item::Group { .. } | Item::AtRule(_) => &[],
+        })
+    }
+
+    /// Reglas con propiedades de diseño cuyos selectores se pueden comprobar
+    /// sobre el árbol (sin pseudoclases), en orden de aparición.
+    pub fn layout_rules(&self) -> Vec<LayoutRule> {
+        let mut rules = Vec::new();
+
+        for item in &self.items {
+            let Item::Rule(rule) = item else {
+                continue;
+            };
+
+            let declarations: Vec<Declaration> = rule
+                .declarations
+                .iter()
+                .filter(|declaration| is_layout_property(&declaration.name))
+                .cloned()
+                .collect();
+            if declarations.is_empty() {
+                continue;
+            }
+
+            for selector in rule.selectors.iter().filter_map(|selector| Selector::parse(selector)) {
+                rules.push(LayoutRule {
+                    order: rules.len(),
+                    selector,
+                    declarations: declarations.clone(),
+                });
+            }
+        }
+
+        rules
+    }
+}
+
+fn write_rule(css: &mut String, selectors: &[String], declarations: &[Declaration], scope: Option<&str>) {
+    if declarations.is_empty() {
+        return;
+    }
+
+    let selectors: Vec<String> = match scope {
+        Some(scope) => selectors.iter().map(|selector| scope_selector(selector, scope)).collect(),
+        None => selectors.to_vec(),
+    };
+    let _ = writeln!(css, "{} {{ {}; }}", selectors.join(", "), format_declarations(declarations));
+}
+
+// Las declaraciones son de la regla; los bloques anidados se convierten en
+// reglas propias que van detrás, como en CSS
+fn parse_rule(selectors: Vec<String>, body: &str, items: &mut Vec<Item>) {
+    let index = items.len();
+    items.push(Item::Rule(Rule {
+        selectors: selectors.clone(),
+        declarations: Vec::new(),
+    }));
+
+    let mut declarations = Vec::new();
+    let mut rest = body;
+
+    loop {
+        let Some(end) = find_outside_strings(rest, |c| c == '{' || c == ';') else {
+            declarations.extend(parse_declaration(rest));
+            break;
+        };
+
+        if rest[end..].starts_with(';') {
+            declarations.extend(parse_declaration(&rest[..end]));
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        let prelude = rest[..end].trim();
+        let block_end = block_end(rest, end);
+        let nested = block_content(rest, end, block_end);
+
+        if is_group_rule(prelude) {
+            // `@media` dentro de una regla envuelve a la regla entera
+            let mut group = Vec::new();
+            parse_rule(selectors.clone(), nested, &mut group);
+            items.push(Item::Group {
+                prelude: prelude.to_string(),
+                sheet: StyleSheet { items: group },
+            });
+        } else if prelude.starts_with('@') {
+            items.push(Item::AtRule(format!("{} {{ {} {{{}}} }}", prelude, selectors.join(", "), nested)));
+        } else {
+            parse_rule(nest_selectors(&selectors, prelude), nested, items);
+        }
+        rest = &rest[block_end..];
+    }
+
+    if let Some(Item::Rule(rule)) = items.get_mut(index) {
+        rule.declarations = declarations;
+    }
+}
+
+// `@media (...)`, `@supports (...)`
+fn is_group_rule(prelude: &str) -> bool {
+    GROUP_AT_RULES.iter().any(|name| at_rule_is(prelude, name))
+}
+
+// El nombre de la regla `@` es `name`, no solo empieza igual
+fn at_rule_is(text: &str, name: &str) -> bool {
+    text.strip_prefix(name)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(|c: char| c.is_whitespace() || c == '(' || c == '{' || c == ';'))
+}
+
+// `&` es el selector padre; sin `&` el hijo es un descendiente
+fn nest_selectors(parents: &[String], prelude: &str) -> Vec<String> {
+    let children = split_selectors(prelude).into_iter().map(str::trim).filter(|s| !s.is_empty());
+
+    if parents.is_empty() {
+        return children.map(|child| child.replace('&', "").trim().to_string()).collect();
+    }
+
+    children
+        .flat_map(|child| {
+            parents.iter().map(move |parent| {
+                if child.contains('&') {
+                    child.replace('&', parent)
+                } else {
+                    format!("{} {}", parent, child)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Valores de las propiedades personalizadas (`--accent`) para resolver `var()`.
+#[derive(Debug, Clone, Default)]
+pub struct Variables(HashMap<String, String>);
+
+impl Variables {
+    /// Sustituye las `var(--nombre, alternativa)` del valor. `None` si alguna
+    /// no está definida y no tiene alternativa, como una declaración inválida.
+    pub fn resolve(&self, value: &str) -> Option<String> {
+        self.resolve_depth(value, 0)
+    }
+
+    fn resolve_depth(&self, value: &str, depth: usize) -> Option<String> {
+        if depth > MAX_VAR_DEPTH {
+            return None;
+        }
+
+        let mut output = String::with_capacity(value.len());
+        let mut rest = value;
+
+        while let Some(start) = rest.find("var(") {
+            let args_start = start + 4;
+            let close = args_start + matching_paren(&rest[args_start..])?;
+            let args = &rest[args_start..close];
+
+            let (name, fallback) = match args.split_once(',') {
+                Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+                None => (args.trim(), None),
+            };
+            let replacement = match (self.0.get(name), fallback) {
+                (Some(value), _) => self.resolve_depth(value, depth + 1)?,
+                (None, Some(fallback)) => self.resolve_depth(fallback, depth + 1)?,
+                (None, None) => return None,
+            };
+
+            output.push_str(&rest[..start]);
+            output.push_str(&replacement);
+            rest = &rest[close + 1..];
+        }
+
+        output.push_str(rest);
+        Some(output)
+    }
+}
+
+// Posición del `)` que cierra un paréntesis ya abierto
+fn matching_paren(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Regla de la hoja de estilos con propiedades de diseño.
+#[derive(Debug, Clone)]
+pub struct LayoutRule {
+    selector: Selector,
+    order: usize,
+    declarations: Vec<Declaration>,
+}
+
+/// Devuelve una copia del árbol en la que el `style` de cada elemento empieza
+/// por las propiedades de diseño de las reglas que lo seleccionan (ordenadas
+/// por especificidad) seguidas de las suyas propias, con las `var()` ya
+/// resueltas. `text-align` se hereda de los padres como en CSS.
+pub fn compute_styles(body: &DomNode, rules: &[LayoutRule], variables: &Variables) -> DomNode {
+    let mut chain = vec![None];
+    compute_node(body, rules, variables, &[], &mut chain)
+}
+
+fn compute_node<'a>(
+    node: &'a DomNode,
+    rules: &[LayoutRule],
+    variables: &Variables,
+    inherited: &[Declaration],
+    chain: &mut Vec<Option<&'a DomNode>>,
+) -> DomNode {
+    if node.tag_name == "text" {
+        return node.clone();
+    }
+    chain.push(Some(node));
+
+    let mut matched: Vec<&LayoutRule> = rules.iter().filter(|rule| rule.selector.matches(chain)).collect();
+    matched.sort_by_key(|rule| (rule.selector.specificity(), rule.order));
+
+    let inline = node.attributes.get("style").map(|style| parse_declarations(style)).unwrap_or_default();
+
+    // Las propiedades personalizadas del propio `style` solo valen para él
+    let mut local = variables.clone();
+    for declaration in inline.iter().filter(|d| d.is_custom()) {
+        local.0.insert(declaration.name.clone(), declaration.value.clone());
+    }
+
+    let own: Vec<Declaration> = matched
+        .iter()
+        .flat_map(|rule| rule.declarations.iter())
+        .chain(inline.iter())
+        .filter(|declaration| !declaration.is_custom())
+        .filter_map(|declaration| {
+            Some(Declaration {
+                name: declaration.name.clone(),
+                value: local.resolve(&declaration.value)?,
+            })
+        })
+        .collect();
+
+    let mut declarations: Vec<Declaration> = inherited
+        .iter()
+        .filter(|parent| !own.iter().any(|declaration| declaration.name == parent.name))
+        .cloned()
+        .collect();
+    declarations.extend(own);
+
+    let inherited: Vec<Declaration> = INHERITED_LAYOUT_PROPERTIES
+        .iter()
+        .filter_map(|name| declarations.iter().rev().find(|declaration| declaration.name == *name))
+        .cloned()
+        .collect();
+
+    let children = node
+        .children
+        .iter()
+        .map(|child| compute_node(child, rules, variables, &inherited, chain))
+        .collect();
+    chain.pop();
+
+    let mut computed = DomNode {
+        children,
+        ..node.clone()
+    };
+    if declarations.is_empty() {
+        computed.attributes.remove("style");
+    } else {
+        computed.attributes.insert("style".to_string(), format_declarations(&declarations));
+    }
+    computed
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+/// Parte de un selector sin combinadores, como `div.card#main`.
+#[derive(Debug, Clone, Default)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attributes: Vec<(String, Option<String>)>,
+    /// `window` o `:root`: la ventana, por encima del body.
+    root: bool,
+}
+
+impl Compound {
+    fn is_empty(&self) -> bool {
+        self.tag.is_none() && self.id.is_none() && self.classes.is_empty() && self.attributes.is_empty() && !self.root
+    }
+
+    // `None` es la ventana
+    fn matches(&self, node: Option<&DomNode>) -> bool {
+        let Some(node) = node else {
+            return self.root && self.id.is_none() && self.classes.is_empty() && self.attributes.is_empty();
+        };
+        if self.root {
+            return false;
+        }
+
+        self.tag.as_ref().is_none_or(|tag| *tag == node.tag_name)
+            && self.id.as_ref().is_none_or(|id| node.attributes.get("id") == Some(id))
+            && self.classes.iter().all(|class| {
+                node.attributes
+                    .get("class")
+                    .is_some_and(|classes| classes.split_whitespace().any(|c| c == class))
+            })
+            && self.attributes.iter().all(|(name, value)| match value {
+                Some(value) => node.attributes.get(name) == Some(value),
+                None => node.attributes.contains_key(name),
+            })
+    }
+}
+
+/// Selector que se puede comprobar sobre el árbol: etiquetas, `#id`, `.clase`,
+/// `[atributo]`, `[atributo=valor]`, `:root` y los combinadores de
+/// descendiente y `>`. Las pseudoclases dependen del estado del widget, así
+/// que esos selectores no se comprueban.
+#[derive(Debug, Clone)]
+struct Selector {
+    compounds: Vec<Compound>,
+    /// `combinators[i]` une `compounds[i]` con `compounds[i + 1]`.
+    combinators: Vec<Combinator>,
+}
+
+impl Selector {
+    fn parse(selector: &str) -> Option<Selector> {
+        let mut compounds = Vec::new();
+        let mut combinators = Vec::new();
+        let mut current = Compound::default();
+        let mut pending: Option<Combinator> = None;
+        let mut chars = selector.trim().chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() || c == '>' {
+                if !current.is_empty() {
+                    compounds.push(std::mem::take(&mut current));
+                }
+                if c == '>' {
+                    pending = Some(Combinator::Child);
+                } else if pending.is_none() {
+                    pending = Some(Combinator::Descendant);
+                }
+                continue;
+            }
+
+            if let Some(combinator) = pending.take() {
+                if compounds.is_empty() {
+                    return None;
+                }
+                combinators.push(combinator);
+            }
+
+            match c {
+                '*' => {}
+                '#' => current.id = Some(read_ident(&mut chars)?),
+                '.' => current.classes.push(read_ident(&mut chars)?),
+                '[' => {
+                    let inner: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                    let attribute = match inner.split_once('=') {
+                        Some((name, _)) if name.ends_with(|c: char| "~|^$*".contains(c)) => return None,
+                        Some((name, value)) => (
+                            name.trim().to_string(),
+                            Some(value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()),
+                        ),
+                        None => (inner.trim().to_string(), None),
+                    };
+                    current.attributes.push(attribute);
+                }
+                ':' => {
+                    if read_ident(&mut chars)? != "root" {
+                        return None;
+                    }
+                    current.root = true;
+                }
+                c if is_ident_char(c) => {
+                    let mut tag = c.to_string();
+                    tag.push_str(&read_ident(&mut chars).unwrap_or_default());
+                    if tag == "window" {
+                        current.root = true;
+                    } else {
+                        current.tag = Some(tag);
+                    }
+                }
+                // `+`, `~` y demás no se comprueban
+                _ => return None,
+            }
+        }
+
+        if pending.is_some() && current.is_empty() {
+            return None;
+        }
+        compounds.push(current);
+
+        Some(Selector { compounds, combinators })
+    }
+
+    // (ids, clases/atributos, etiquetas), como en CSS
+    fn specificity(&self) -> (usize, usize, usize) {
+        self.compounds.iter().fold((0, 0, 0), |(ids, classes, tags), compound| {
+            (
+                ids + compound.id.is_some() as usize,
+                classes + compound.classes.len() + compound.attributes.len() + compound.root as usize,
+                tags + compound.tag.is_some() as usize,
+            )
+        })
+    }
+
+    /// `chain` va de la ventana (`None`) al elemento que se comprueba.
+    fn matches(&self, chain: &[Option<&DomNode>]) -> bool {
+        self.matches_from(self.compounds.len() - 1, chain)
+    }
+
+    fn matches_from(&self, index: usize, chain: &[Option<&DomNode>]) -> bool {
+        let Some((&node, ancestors)) = chain.split_last() else {
+            return false;
+        };
+        if !self.compounds[index].matches(node) {
+            return false;
+        }
+        if index == 0 {
+            return true;
+        }
+
+        match self.combinators[index - 1] {
+            Combinator::Child => self.matches_from(index - 1, ancestors),
+            Combinator::Descendant => (1..=ancestors.len()).rev().any(|end| self.matches_from(index - 1, &ancestors[..end])),
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '-' || c == '_'
+}
+
+fn read_ident(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    let mut ident = String::new();
+    while let Some(&c) = chars.peek() {
+        if !is_ident_char(c) {
+            break;
+        }
+        ident.push(c);
+        chars.next();
+    }
+    (!ident.is_empty()).then_some(ident)
+}
+
+/// Limita el selector a la ventana con la clase `scope` anteponiendo
+/// `window.<scope>` (`window` y `:root` pasan a ser la propia ventana).
+pub fn scope_selector(selector: &str, scope: &str) -> String {
+    let root = format!("window.{}", scope);
+
+    if selector == ":root" {
+        return root;
+    }
+    if let Some(rest) = selector.strip_prefix(":root") {
+        if !rest.starts_with(is_ident_char) {
+            return format!("{}{}", root, rest);
+        }
+    }
+    if let Some(rest) = selector.strip_prefix("window") {
+        if !rest.starts_with(is_ident_char) {
+            return format!("{}{}", root, rest);
+        }
+    }
+    format!("{} {}", root, selector)
+}
+
+// Separa por comas que no estén dentro de paréntesis (`:not(.a, .b)`)
+fn split_selectors(selectors: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in selectors.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&selectors[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&selectors[start..]);
+    parts
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = find_outside_strings(rest, |c| c == '/') {
+        if !rest[start..].starts_with("/*") {
+            output.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        }
+
+        output.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Posición del primer carácter que cumple `matches` fuera de comillas
+fn find_outside_strings(css: &str, matches: impl Fn(char) -> bool) -> Option<usize> {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in css.char_indices() {
+        match quote {
+            Some(_) if escaped => escaped = false,
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if matches(c) => return Some(i),
+            None => {}
+        }
+    }
+
+    None
+}
+
+// Fin (exclusivo) del bloque `{ ... }` que empieza en `open`, con bloques anidados
+fn block_end(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+    let mut pos = open;
+
+    while let Some(found) = find_outside_strings(&css[pos..], |c| c == '{' || c == '}') {
+        let i = pos + found;
+        if css[i..].starts_with('{') {
+            depth += 1;
+        } else {
+            depth -= 1;
+            if depth == 0 {
+                return i + 1;
+            }
+        }
+        pos = i + 1;
+    }
+
+    css.len()
+}
+
+// Contenido entre las llaves de un bloque (aunque falte la de cierre)
+fn block_content(css: &str, open: usize, end: usize) -> &str {
+    let close = if css[..end].ends_with('}') { end - 1 } else { end };
+    &css[open + 1..close.max(open + 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, attributes: &[(&str, &str)], children: Vec<DomNode>) -> DomNode {
+        DomNode {
+            tag_name: tag.to_string(),
+            attributes: attributes.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            children,
+            ..Default::default()
+        }
+    }
+
+    // `style` calculado de cada elemento, en orden de documento
+    fn styles(node: &DomNode, output: &mut Vec<String>) {
+        if node.tag_name == "text" {
+            return;
+        }
+        output.push(node.attributes.get("style").cloned().unwrap_or_default());
+        for child in &node.children {
+            styles(child, output);
+        }
+    }
+
+    fn computed(css: &str, body: &DomNode) -> Vec<String> {
+        let sheet = StyleSheet::parse(css);
+        let mut variables = Variables::default();
+        sheet.collect_variables(&mut variables);
+
+        let mut output = Vec::new();
+        styles(&compute_styles(body, &sheet.layout_rules(), &variables), &mut output);
+        output
+    }
+
+    #[test]
+    fn declarations_skip_comments_and_keep_strings() {
+        let declarations = parse_declarations("COLOR: red; /* a; b */ content: \"x; y\"; --Accent: Blue; broken; : x");
+
+        assert_eq!(
+            declarations,
+            [
+                Declaration { name: "color".to_string(), value: "red".to_string() },
+                Declaration { name: "content".to_string(), value: "\"x; y\"".to_string() },
+                Declaration { name: "--Accent".to_string(), value: "Blue".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_rules_are_flattened() {
+        let sheet = StyleSheet::parse(
+            ".card, .panel { color: red; width: 10px; &:hover { color: blue; } .title { color: green; } @media (min-width: 1px) { color: black; } }",
+        );
+
+        assert_eq!(
+            sheet.to_gtk_css(None, &Variables::default()),
+            ".card, .panel { color: red; }\n\
+             .card:hover, .panel:hover { color: blue; }\n\
+             .card .title, .panel .title { color: green; }\n\
+             @media (min-width: 1px) { .card, .panel { color: black; } }\n"
+        );
+    }
+
+    #[test]
+    fn gtk_css_resolves_variables_and_scopes_selectors() {
+        let sheet = StyleSheet::parse(
+            ":root { --accent: var(--base); --base: red; } window { color: var(--accent); } label { color: var(--missing); } button:hover { opacity: 0.5; width: 1px; }",
+        );
+        let mut variables = Variables::default();
+        sheet.collect_variables(&mut variables);
+
+        assert_eq!(
+            sheet.to_gtk_css(Some("w1"), &variables),
+            "window.w1 { color: red; }\nwindow.w1 button:hover { opacity: 0.5; }\n"
+        );
+    }
+
+    #[test]
+    fn at_rules_are_scoped_or_dropped() {
+        let sheet = StyleSheet::parse(
+            "@import url(\"other.css\"); @define-color accent red; \
+             @media (prefers-color-scheme: dark) { button { color: red; width: 1px; } } \
+             .card { @media (min-width: 1px) { color: black; } } \
+             @keyframes spin { from { opacity: 0; } }",
+        );
+
+        assert_eq!(
+            sheet.to_gtk_css(Some("w1"), &Variables::default()),
+            "@media (prefers-color-scheme: dark) { window.w1 button { color: red; } }\n\
+             @media (min-width: 1px) { window.w1 .card { color: black; } }\n\
+             @keyframes spin { from { opacity: 0; } }\n"
+        );
+        assert!(sheet.to_gtk_css(None, &Variables::default()).starts_with("@import url(\"other.css\");\n@define-color accent red;\n"));
+    }
+
+    #[test]
+    fn variables_use_fallbacks_and_stop_on_cycles() {
+        let sheet = StyleSheet::parse(":root { --a: var(--b); --b: var(--a); --size: 4px; }");
+        let mut variables = Variables::default();
+        sheet.collect_variables(&mut variables);
+
+        assert_eq!(variables.resolve("calc(var(--size) * 2)").as_deref(), Some("calc(4px * 2)"));
+        assert_eq!(variables.resolve("var(--none, var(--size))").as_deref(), Some("4px"));
+        assert_eq!(variables.resolve("var(--none)"), None);
+        assert_eq!(variables.resolve("var(--a)"), None);
+    }
+
+    #[test]
+    fn selectors_match_descendants_children_and_attributes() {
+        let body = element(
+            "body",
+            &[],
+            vec![element(
+                "div",
+                &[("class", "card big"), ("id", "main")],
+                vec![
+                    element("p", &[("data-kind", "note")], vec![]),
+                    element("section", &[], vec![element("p", &[], vec![])]),
+                ],
+            )],
+        );
+        let css = "body p { width: 1px; } .card > p { height: 2px; } [data-kind=note] { gap: 3px; } div.big#main { display: flex; } span { width: 9px; }";
+
+        assert_eq!(computed(css, &body), ["", "display: flex", "width: 1px; gap: 3px; height: 2px", "", "width: 1px"]);
+    }
+
+    #[test]
+    fn specificity_then_order_then_inline() {
+        let body = element("body", &[], vec![element("p", &[("id", "x"), ("class", "a"), ("style", "width: 4px")], vec![])]);
+        let css = "#x { width: 1px; } .a { width: 2px; } p { width: 3px; } .a { height: 1px; } p.a { height: 2px; }";
+
+        assert_eq!(computed(css, &body), ["", "width: 3px; width: 2px; height: 1px; height: 2px; width: 1px; width: 4px"]);
+    }
+
+    #[test]
+    fn text_align_is_inherited() {
+        let body = element(
+            "body",
+            &[],
+            vec![element("div", &[("class", "center")], vec![element("p", &[], vec![]), element("p", &[("style", "text-align: end")], vec![])])],
+        );
+
+        assert_eq!(
+            computed(".center { text-align: center; width: 5px; }", &body),
+            ["", "text-align: center; width: 5px", "text-align: center", "text-align: end"]
+        );
+    }
+
+    #[test]
+    fn pseudo_classes_and_sibling_combinators_are_not_matched() {
+        assert!(Selector::parse("p:hover").is_none());
+        assert!(Selector::parse("p + p").is_none());
+        assert!(Selector::parse("[href^=http]").is_none());
+        assert!(Selector::parse(":root > p").is_some());
+        assert_eq!(scope_selector(":root", "w"), "window.w");
+        assert_eq!(scope_selector("window > box", "w"), "window.w > box");
+        assert_eq!(scope_selector("windows", "w"), "window.w windows");
+    }
+}
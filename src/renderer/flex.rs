@@ -0,0 +1,376 @@
+use gtk4::prelude::*;
+use gtk4::{Align, Box as GtkBox, FlowBox, Label, Orientation, SelectionMode, Widget};
+
+use super::css::parse_declarations;
+use super::gtk_renderer::render_dom_to_gtk;
+use super::inline::{group_inline, inline_label, Flow};
+use super::inline_flow::{has_inline_flow, render_inline_flow};
+use super::layout::parse_px;
+use super::overlay::{split_absolute, with_absolute_layer};
+use crate::parser::html_parser::DomNode;
+
+/// Reparto del espacio sobrante en el eje principal (`justify`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+/// Disposición de un contenedor, de sus atributos (`direction`, `gap`,
+/// `align`, `justify`, `wrap`) o de las propiedades CSS equivalentes, que
+/// tienen prioridad como en HTML.
+struct FlexLayout {
+    direction: Orientation,
+    /// `row-reverse`/`column-reverse`: los hijos van del final al principio.
+    reverse: bool,
+    row_gap: i32,
+    column_gap: i32,
+    /// `None` es `stretch`: los hijos ocupan todo el eje secundario.
+    align: Option<Align>,
+    justify: Justify,
+    wrap: bool,
+}
+
+impl FlexLayout {
+    fn from_node(node: &DomNode, default_gap: i32) -> Self {
+        let mut layout = FlexLayout {
+            direction: Orientation::Vertical,
+            reverse: false,
+            row_gap: default_gap,
+            column_gap: default_gap,
+            align: None,
+            justify: Justify::Start,
+            wrap: false,
+        };
+
+        let attributes = ["direction", "gap", "align", "justify", "wrap"]
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), node.attributes.get(name)?.clone())));
+        let style = node
+            .attributes
+            .get("style")
+            .map(|style| parse_declarations(style))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|declaration| (declaration.name, declaration.value));
+
+        for (name, value) in attributes.chain(style) {
+            layout.set(&name, value.trim());
+        }
+
+        layout
+    }
+
+    fn set(&mut self, name: &str, value: &str) {
+        match name {
+            "direction" | "flex-direction" => {
+                if let Some((direction, reverse)) = parse_direction(value) {
+                    self.direction = direction;
+                    self.reverse = reverse;
+                }
+            }
+            "gap" => {
+                let mut values = value.split_whitespace();
+                if let Some(row_gap) = values.next().and_then(parse_px) {
+                    self.row_gap = row_gap;
+                    self.column_gap = values.next().and_then(parse_px).unwrap_or(row_gap);
+                }
+            }
+            "row-gap" => self.row_gap = parse_px(value).unwrap_or(self.row_gap),
+            "column-gap" => self.column_gap = parse_px(value).unwrap_or(self.column_gap),
+            "align" | "align-items" => self.align = parse_align(value).unwrap_or(self.align),
+            "justify" | "justify-content" => self.justify = parse_justify(value).unwrap_or(self.justify),
+            "wrap" | "flex-wrap" => self.wrap = parse_wrap(value).unwrap_or(self.wrap),
+            _ => {}
+        }
+    }
+
+    // Separación entre hijos consecutivos
+    fn gap(&self) -> i32 {
+        match self.direction {
+            Orientation::Horizontal => self.column_gap,
+            _ => self.row_gap,
+        }
+    }
+}
+
+/// Separación entre filas y entre columnas de un contenedor (`gap`,
+/// `row-gap`, `column-gap`).
+pub fn gaps(node: &DomNode, default_gap: i32) -> (i32, i32) {
+    let layout = FlexLayout::from_node(node, default_gap);
+    (layout.row_gap, layout.column_gap)
+}
+
+/// Error de un ajuste de contenedor (`direction`, `align`, `justify`,
+/// `wrap` o su CSS) o de hijo (`grow`, `shrink`) que no se podrá aplicar.
+/// `FlexLayout` ignora esos valores; se avisa una vez al cargar el widget.
+pub(crate) fn invalid_flex_value(name: &str, value: &str) -> Option<String> {
+    let expected = match name {
+        "direction" | "flex-direction" if parse_direction(value).is_none() => "row, row-reverse, column o column-reverse",
+        "align" | "align-items" if parse_align(value).is_none() => "stretch, start, center o end",
+        "justify" | "justify-content" if parse_justify(value).is_none() => {
+            "start, center, end, space-between, space-around o space-evenly"
+        }
+        "wrap" | "flex-wrap" if parse_wrap(value).is_none() => "wrap o nowrap",
+        "grow" | "flex-grow" | "shrink" | "flex-shrink" if value.parse::<f32>().is_err() => "un número",
+        _ => return None,
+    };
+    Some(format!("se esperaba {}, no \"{}\"", expected, value))
+}
+
+fn parse_direction(value: &str) -> Option<(Orientation, bool)> {
+    match value {
+        "row" | "row-reverse" => Some((Orientation::Horizontal, value.ends_with("-reverse"))),
+        "column" | "column-reverse" => Some((Orientation::Vertical, value.ends_with("-reverse"))),
+        _ => None,
+    }
+}
+
+fn parse_align(value: &str) -> Option<Option<Align>> {
+    match value {
+        "stretch" => Some(None),
+        "start" | "flex-start" => Some(Some(Align::Start)),
+        "center" => Some(Some(Align::Center)),
+        "end" | "flex-end" => Some(Some(Align::End)),
+        _ => None,
+    }
+}
+
+fn parse_justify(value: &str) -> Option<Justify> {
+    match value {
+        "start" | "flex-start" => Some(Justify::Start),
+        "center" => Some(Justify::Center),
+        "end" | "flex-end" => Some(Justify::End),
+        "space-between" => Some(Justify::SpaceBetween),
+        "space-around" => Some(Justify::SpaceAround),
+        "space-evenly" => Some(Justify::SpaceEvenly),
+        _ => None,
+    }
+}
+
+// `<div wrap>` sin valor también activa el salto de línea
+fn parse_wrap(value: &str) -> Option<bool> {
+    match value {
+        "" | "wrap" | "true" => Some(true),
+        "nowrap" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+/// Hijo de un contenedor: un elemento o un tramo de texto en línea (sin nodo).
+pub struct FlexItem<'a> {
+    pub widget: Widget,
+    pub node: Option<&'a DomNode>,
+}
+
+/// Widgets de los hijos del nodo, con el texto en línea consecutivo en un
+/// único `Label`.
+pub fn flex_items(node: &DomNode) -> Vec<FlexItem<'_>> {
+    group_inline(&node.children)
+        .into_iter()
+        .map(|item| match item {
+            Flow::Inline(nodes) => FlexItem {
+                widget: inline_label(&nodes).upcast(),
+                node: None,
+            },
+            Flow::Block(child) => FlexItem {
+                widget: render_dom_to_gtk(child),
+                node: Some(child),
+            },
+        })
+        .collect()
+}
+
+/// Contenedor flexible con nodo CSS `node.tag_name`: un `GtkBox` en una fila
+/// o columna, o un `GtkFlowBox` si los hijos pasan a la línea siguiente. Si
+/// mezcla texto con `<img>` o `<button>`, un contexto en línea (ver
+/// `has_inline_flow`). Márgenes y separación son 0 salvo `default_gap` o lo que digan sus
+/// atributos y estilos. Los hijos con `position="absolute"` van encima, en
+/// un `GtkOverlay` que pasa a ser el nodo `node.tag_name`.
+pub fn render_flex(node: &DomNode, items: Vec<FlexItem>, default_gap: i32) -> Widget {
+    let (items, absolute) = split_absolute(items);
+    let css_name = if absolute.is_empty() { node.tag_name.as_str() } else { "box" };
+
+    // Texto mezclado con imágenes o botones fluye en líneas, como en HTML
+    let container = if has_inline_flow(node) {
+        render_inline_flow(css_name, node, items)
+    } else {
+        render_container(css_name, node, items, default_gap)
+    };
+
+    if absolute.is_empty() {
+        container
+    } else {
+        with_absolute_layer(&node.tag_name, container, absolute)
+    }
+}
+
+fn render_container(css_name: &str, node: &DomNode, mut items: Vec<FlexItem>, default_gap: i32) -> Widget {
+    let mut layout = FlexLayout::from_node(node, default_gap);
+
+    // Al invertir el orden también se invierte el eje: `start` pasa a ser el final
+    if layout.reverse {
+        items.reverse();
+        layout.justify = match layout.justify {
+            Justify::Start => Justify::End,
+            Justify::End => Justify::Start,
+            justify => justify,
+        };
+    }
+
+    for item in &items {
+        if let Some(child) = item.node {
+            apply_flex_child(&item.widget, child, &layout);
+        }
+        // `halign`/`valign` en el propio hijo ganan a `align` del contenedor
+        let own = |name| item.node.is_some_and(|child| child.attributes.contains_key(name));
+        if let Some(align) = layout.align {
+            match layout.direction {
+                Orientation::Horizontal if !own("valign") => item.widget.set_valign(align),
+                Orientation::Vertical if !own("halign") => item.widget.set_halign(align),
+                _ => {}
+            }
+        }
+    }
+
+    if layout.wrap {
+        render_flow_box(css_name, items, &layout)
+    } else {
+        render_box(css_name, items, &layout)
+    }
+}
+
+fn render_box(css_name: &str, items: Vec<FlexItem>, layout: &FlexLayout) -> Widget {
+    let container = GtkBox::builder()
+        .css_name(css_name)
+        .orientation(layout.direction)
+        .build();
+
+    let gap = layout.gap();
+    if layout.justify == Justify::Start {
+        container.set_spacing(gap);
+        for item in &items {
+            container.append(&item.widget);
+        }
+        return container.upcast();
+    }
+
+    // GtkBox no reparte el espacio sobrante, así que se hace con separadores
+    // vacíos. Si algún hijo crece se queda él con el espacio, como en CSS
+    let grows = items.iter().any(|item| match layout.direction {
+        Orientation::Horizontal => item.widget.hexpands(),
+        _ => item.widget.vexpands(),
+    });
+    let spacer = |size: i32, expand: bool| {
+        let spacer = GtkBox::new(Orientation::Horizontal, 0);
+        match layout.direction {
+            Orientation::Horizontal => {
+                spacer.set_width_request(size);
+                spacer.set_hexpand(expand && !grows);
+            }
+            _ => {
+                spacer.set_height_request(size);
+                spacer.set_vexpand(expand && !grows);
+            }
+        }
+        spacer
+    };
+
+    // Separadores que crecen al principio, al final y entre hijos; `space-around`
+    // pone dos entre hijos para que ese hueco valga el doble que los bordes
+    let (leading, trailing, between) = match layout.justify {
+        Justify::Start => (false, false, 0),
+        Justify::Center => (true, true, 0),
+        Justify::End => (true, false, 0),
+        Justify::SpaceBetween => (false, false, 1),
+        Justify::SpaceAround => (true, true, 2),
+        Justify::SpaceEvenly => (true, true, 1),
+    };
+
+    if leading {
+        container.append(&spacer(0, true));
+    }
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            match between {
+                0 => container.append(&spacer(gap, false)),
+                parts => {
+                    for _ in 0..parts {
+                        container.append(&spacer(gap / parts, true));
+                    }
+                }
+            }
+        }
+        container.append(&item.widget);
+    }
+    if trailing {
+        container.append(&spacer(0, true));
+    }
+
+    container.upcast()
+}
+
+fn render_flow_box(css_name: &str, items: Vec<FlexItem>, layout: &FlexLayout) -> Widget {
+    // La orientación de GtkFlowBox es la de sus líneas
+    let flow_box = FlowBox::builder()
+        .css_name(css_name)
+        .orientation(layout.direction)
+        .selection_mode(SelectionMode::None)
+        .row_spacing(layout.row_gap.max(0) as u32)
+        .column_spacing(layout.column_gap.max(0) as u32)
+        .max_children_per_line(items.len().max(1) as u32)
+        .build();
+
+    // Con salto de línea solo se puede agrupar al principio, centro o final
+    let align = match layout.justify {
+        Justify::Start => Align::Start,
+        Justify::Center => Align::Center,
+        Justify::End => Align::End,
+        _ => Align::Fill,
+    };
+    match layout.direction {
+        Orientation::Horizontal => flow_box.set_halign(align),
+        _ => flow_box.set_valign(align),
+    }
+
+    for item in items {
+        flow_box.append(&item.widget);
+    }
+
+    flow_box.upcast()
+}
+
+// `grow` hace que el hijo se quede con el espacio sobrante del eje principal
+// (a partes iguales entre todos los que crecen) y `shrink="0"` evita que su
+// texto se parta para caber
+fn apply_flex_child(widget: &Widget, child: &DomNode, layout: &FlexLayout) {
+    let mut grow = child.attributes.get("grow").cloned();
+    let mut shrink = child.attributes.get("shrink").cloned();
+
+    if let Some(style) = child.attributes.get("style") {
+        for declaration in parse_declarations(style) {
+            match declaration.name.as_str() {
+                "flex-grow" => grow = Some(declaration.value),
+                "flex-shrink" => shrink = Some(declaration.value),
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(grow) = grow.and_then(|grow| grow.trim().parse::<f32>().ok()) {
+        match layout.direction {
+            Orientation::Horizontal => widget.set_hexpand(grow > 0.0),
+            _ => widget.set_vexpand(grow > 0.0),
+        }
+    }
+
+    if shrink.and_then(|shrink| shrink.trim().parse::<f32>().ok()) == Some(0.0) {
+        if let Some(label) = widget.downcast_ref::<Label>() {
+            label.set_wrap(false);
+        }
+    }
+}
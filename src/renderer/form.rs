@@ -0,0 +1,180 @@
+use gtk4::prelude::*;
+use gtk4::{glib, Button, Widget};
+use serde_json::{Map, Value};
+
+use super::actions::{event_handler, Actions};
+use super::flex::{flex_items, render_flex};
+use super::inputs::{input_of, input_value, inputs_in, Input, InputKind, InputValue};
+use crate::parser::html_parser::DomNode;
+
+/// Prefijo de las variables de entorno con los valores del formulario.
+const ENV_PREFIX: &str = "FORM_";
+
+/// Clase CSS de los controles que no pasan la validación.
+const INVALID_CLASS: &str = "invalid";
+
+/// `<form onsubmit="...">`: contenedor como `<div>` que, al pulsar un botón
+/// de envío o Intro en un campo de texto, valida sus controles y ejecuta las
+/// acciones de `onsubmit` con los valores de los que tienen `name`.
+pub fn render_form(node: &DomNode) -> Widget {
+    let form = render_flex(node, flex_items(node), 0);
+    let onsubmit = event_handler(node, "onsubmit");
+
+    for input in inputs_in(&form) {
+        // El formulario es dueño de sus controles; con una referencia fuerte
+        // nunca se liberaría
+        let form_ref = form.downgrade();
+        let onsubmit = onsubmit.clone();
+        let submit_form = move || {
+            if let Some(form) = form_ref.upgrade() {
+                submit(&form, onsubmit.as_ref());
+            }
+        };
+
+        match input_of(&input).map(|input| input.kind) {
+            Some(InputKind::Submit) => {
+                if let Some(button) = input.downcast_ref::<Button>() {
+                    button.connect_clicked(move |_| submit_form());
+                }
+            }
+            Some(InputKind::Text) => {
+                input.connect_local("activate", false, move |_| {
+                    submit_form();
+                    None
+                });
+            }
+            _ => {}
+        }
+    }
+
+    form
+}
+
+/// Valores de los controles con `name` de un formulario, en orden de documento.
+pub struct FormData(Vec<(String, InputValue)>);
+
+impl FormData {
+    /// Un `radio` solo cuenta si está marcado; un `checkbox` siempre, como booleano.
+    pub fn collect(inputs: &[Widget]) -> Self {
+        FormData(
+            inputs
+                .iter()
+                .filter_map(|widget| Some((input_of(widget)?.name?, input_value(widget)?)))
+                .collect(),
+        )
+    }
+
+    /// Objeto JSON `{ "name": valor }`; los números enteros van sin decimales.
+    pub fn to_json(&self) -> Value {
+        let fields = self.0.iter().map(|(name, value)| {
+            let value = match value {
+                InputValue::Text(text) => Value::from(text.as_str()),
+                InputValue::Bool(value) => Value::from(*value),
+                InputValue::Number(number) if number.fract() == 0.0 && number.abs() < i64::MAX as f64 => {
+                    Value::from(*number as i64)
+                }
+                InputValue::Number(number) => Value::from(*number),
+            };
+            (name.clone(), value)
+        });
+        Value::Object(fields.collect::<Map<_, _>>())
+    }
+
+    /// `FORM_NOMBRE=valor` por campo: el nombre en mayúsculas y lo que no
+    /// sea letra o número cambiado por `_`.
+    pub fn env_vars(&self) -> Vec<(String, String)> {
+        self.0
+            .iter()
+            .map(|(name, value)| {
+                let name: String = name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                    .collect();
+                (format!("{}{}", ENV_PREFIX, name), value.to_string())
+            })
+            .collect()
+    }
+
+    /// Valor de `{$name}` (sin llaves), o `None` si no es un campo.
+    pub fn placeholder(&self, key: &str) -> Option<String> {
+        let name = key.strip_prefix('$')?;
+        self.0.iter().find(|(field, _)| field == name).map(|(_, value)| value.to_string())
+    }
+}
+
+// Se validan todos los controles para marcar todos los errores a la vez
+fn submit(form: &Widget, onsubmit: Option<&Actions>) {
+    let inputs = inputs_in(form);
+    let invalid = inputs.iter().filter(|widget| !validate(widget, &inputs)).count();
+    if invalid > 0 {
+        eprintln!("  ⚠ El formulario tiene {} campo(s) inválido(s), no se envía", invalid);
+        return;
+    }
+
+    let data = FormData::collect(&inputs);
+    match onsubmit {
+        Some(actions) => actions.submit(form, &data),
+        None => crate::vprintln!("Formulario sin onsubmit: {}", data.to_json()),
+    }
+}
+
+// Marca el control con la clase `invalid` y el motivo como tooltip, o se
+// los quita si ya es válido
+fn validate(widget: &Widget, inputs: &[Widget]) -> bool {
+    let Some(input) = input_of(widget) else {
+        return true;
+    };
+
+    let error = validation_error(&input, input_value(widget), inputs);
+    match &error {
+        Some(message) => {
+            widget.add_css_class(INVALID_CLASS);
+            widget.set_tooltip_text(Some(message));
+        }
+        None if widget.has_css_class(INVALID_CLASS) => {
+            widget.remove_css_class(INVALID_CLASS);
+            widget.set_tooltip_text(None);
+        }
+        None => {}
+    }
+
+    error.is_none()
+}
+
+fn validation_error(input: &Input, value: Option<InputValue>, inputs: &[Widget]) -> Option<String> {
+    if input.required {
+        let missing = match &input.kind {
+            // Basta con que esté marcado uno del grupo
+            InputKind::Radio(_) => !inputs.iter().any(|other| {
+                let same_group = input_of(other).is_some_and(|other| {
+                    matches!(other.kind, InputKind::Radio(_)) && other.name == input.name
+                });
+                same_group && input_value(other).is_some()
+            }),
+            _ => matches!(value, None | Some(InputValue::Bool(false))) || value.as_ref().is_some_and(is_blank),
+        };
+        if missing {
+            return Some("Este campo es obligatorio".to_string());
+        }
+    }
+
+    // Como en HTML, el patrón tiene que cumplirlo el texto entero
+    if let (Some(pattern), Some(InputValue::Text(text))) = (&input.pattern, &value) {
+        let anchored = format!("^(?:{})$", pattern);
+        let matches = glib::Regex::match_simple(
+            &anchored,
+            text,
+            glib::RegexCompileFlags::DEFAULT,
+            glib::RegexMatchFlags::DEFAULT,
+        );
+        if !text.is_empty() && !matches {
+            return Some(format!("El valor no cumple el formato pedido ({})", pattern));
+        }
+    }
+
+    None
+}
+
+fn is_blank(value: &InputValue) -> bool {
+    matches!(value, InputValue::Text(text) if text.trim().is_empty())
+}
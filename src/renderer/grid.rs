@@ -0,0 +1,201 @@
+use gtk4::prelude::*;
+use gtk4::{Grid, Widget};
+use std::collections::HashSet;
+
+use super::flex::{flex_items, gaps, render_flex};
+use super::gtk_renderer::render_dom_to_gtk;
+use super::inline::{group_inline, inline_label, Flow};
+use crate::parser::html_parser::DomNode;
+
+const TABLE_SECTIONS: &[&str] = &["thead", "tbody", "tfoot"];
+
+/// Celdas ocupadas de la rejilla, para colocar los hijos sin solaparlos.
+#[derive(Default)]
+struct Occupied(HashSet<(i32, i32)>);
+
+impl Occupied {
+    fn is_free(&self, row: i32, col: i32, rowspan: i32, colspan: i32) -> bool {
+        (row..row + rowspan).all(|r| (col..col + colspan).all(|c| !self.0.contains(&(r, c))))
+    }
+
+    fn fill(&mut self, row: i32, col: i32, rowspan: i32, colspan: i32) {
+        for r in row..row + rowspan {
+            for c in col..col + colspan {
+                self.0.insert((r, c));
+            }
+        }
+    }
+}
+
+/// `<grid columns="3">`: los hijos se colocan en orden de izquierda a derecha
+/// y de arriba abajo, o en `row`/`col` (contando desde 1) si los indican, y
+/// ocupan `rowspan` × `colspan` celdas.
+pub fn render_grid(node: &DomNode) -> Widget {
+    let grid = new_grid(node);
+    let columns = span_attribute(node, "columns").max(1);
+
+    let mut occupied = Occupied::default();
+    let (mut cursor_row, mut cursor_col) = (0, 0);
+
+    for item in group_inline(&node.children) {
+        let (widget, child) = match item {
+            Flow::Inline(nodes) => (inline_label(&nodes).upcast::<Widget>(), None),
+            Flow::Block(child) => (render_dom_to_gtk(child), Some(child)),
+        };
+
+        let position = |name| {
+            child
+                .and_then(|child| child.attributes.get(name))
+                .and_then(|value| value.trim().parse::<i32>().ok())
+        };
+        let rowspan = child.map(|child| span_attribute(child, "rowspan")).unwrap_or(1);
+        let colspan = child.map(|child| span_attribute(child, "colspan")).unwrap_or(1).min(columns);
+
+        let (row, col) = match (position("row"), position("col")) {
+            (Some(row), Some(col)) => ((row - 1).max(0), (col - 1).max(0)),
+            (row, col) => {
+                let fixed = (row.map(|row| (row - 1).max(0)), col.map(|col| (col - 1).max(0)));
+                next_free(&occupied, fixed, (cursor_row, cursor_col), rowspan, colspan, columns)
+            }
+        };
+
+        occupied.fill(row, col, rowspan, colspan);
+        grid.attach(&widget, col, row, colspan, rowspan);
+
+        cursor_row = row;
+        cursor_col = col + colspan;
+        if cursor_col >= columns {
+            cursor_row += 1;
+            cursor_col = 0;
+        }
+    }
+
+    grid.upcast()
+}
+
+// Primer hueco libre en orden de lectura desde `start`. Con la fila o la
+// columna fija solo se busca en ella; una fila fija puede pasar de `columns`
+fn next_free(
+    occupied: &Occupied,
+    fixed: (Option<i32>, Option<i32>),
+    start: (i32, i32),
+    rowspan: i32,
+    colspan: i32,
+    columns: i32,
+) -> (i32, i32) {
+    match fixed {
+        (Some(row), _) => {
+            let col = (0..).find(|&col| occupied.is_free(row, col, rowspan, colspan)).unwrap_or(0);
+            (row, col)
+        }
+        (None, Some(col)) => {
+            let row = (start.0..).find(|&row| occupied.is_free(row, col, rowspan, colspan)).unwrap_or(start.0);
+            (row, col)
+        }
+        (None, None) => {
+            let (mut row, mut col) = start;
+            loop {
+                if col + colspan > columns {
+                    row += 1;
+                    col = 0;
+                } else if occupied.is_free(row, col, rowspan, colspan) {
+                    return (row, col);
+                } else {
+                    col += 1;
+                }
+            }
+        }
+    }
+}
+
+/// `<table>` de HTML: filas `<tr>` (sueltas o en `<thead>`, `<tbody>`,
+/// `<tfoot>`) con celdas `<td>`/`<th>` que pueden ocupar varias filas o
+/// columnas. Las filas no son widgets: cada celda es un contenedor con nodo
+/// CSS `td` o `th` y la clase de su sección (`.thead`, `.tbody`, `.tfoot`).
+pub fn render_table(node: &DomNode) -> Widget {
+    let grid = new_grid(node);
+
+    let mut rows: Vec<(&DomNode, Option<&str>)> = Vec::new();
+    for child in &node.children {
+        match child.tag_name.as_str() {
+            "tr" => rows.push((child, None)),
+            section if TABLE_SECTIONS.contains(&section) => {
+                rows.extend(
+                    child
+                        .children
+                        .iter()
+                        .filter(|row| row.tag_name == "tr")
+                        .map(|row| (row, Some(section))),
+                );
+            }
+            // `check_layout` avisa de `<caption>` al cargar
+            _ => {}
+        }
+    }
+
+    // Como en HTML, las celdas saltan los huecos que ocupan los `rowspan` de arriba
+    let mut occupied = Occupied::default();
+    for (row, (tr, section)) in rows.into_iter().enumerate() {
+        let row = row as i32;
+        let mut col = 0;
+
+        for cell in tr.children.iter().filter(|cell| cell.tag_name == "td" || cell.tag_name == "th") {
+            while !occupied.is_free(row, col, 1, 1) {
+                col += 1;
+            }
+
+            let rowspan = span_attribute(cell, "rowspan");
+            let colspan = span_attribute(cell, "colspan");
+
+            let widget = render_dom_to_gtk(cell);
+            if let Some(section) = section {
+                widget.add_css_class(section);
+            }
+
+            occupied.fill(row, col, rowspan, colspan);
+            grid.attach(&widget, col, row, colspan, rowspan);
+            col += colspan;
+        }
+    }
+
+    grid.upcast()
+}
+
+/// Celda de tabla, fuera o dentro de una: un contenedor como `<div>`.
+pub fn render_cell(node: &DomNode) -> Widget {
+    render_flex(node, flex_items(node), 0)
+}
+
+// `homogeneous` da a todas las columnas el mismo ancho; si no, cada una se
+// ajusta a su contenido
+fn new_grid(node: &DomNode) -> Grid {
+    let (row_gap, column_gap) = gaps(node, 0);
+    let homogeneous = node
+        .attributes
+        .get("homogeneous")
+        .is_some_and(|value| matches!(value.trim(), "" | "true"));
+
+    Grid::builder()
+        .css_name(node.tag_name.as_str())
+        .row_spacing(row_gap)
+        .column_spacing(column_gap)
+        .column_homogeneous(homogeneous)
+        .build()
+}
+
+/// Error de `columns`, `rowspan` o `colspan` si no es un número mayor que 0.
+/// Al renderizar esos valores cuentan como 1; se avisa una vez al cargar.
+pub(crate) fn invalid_span(value: &str) -> Option<String> {
+    match parse_span(value) {
+        Some(_) => None,
+        None => Some(format!("debe ser un número mayor que 0, no \"{}\"", value)),
+    }
+}
+
+fn parse_span(value: &str) -> Option<i32> {
+    value.trim().parse::<i32>().ok().filter(|span| *span >= 1)
+}
+
+fn span_attribute(node: &DomNode, name: &str) -> i32 {
+    node.attributes.get(name).and_then(|value| parse_span(value)).unwrap_or(1)
+}
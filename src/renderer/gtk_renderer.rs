@@ -1,75 +1,124 @@
 use gtk4::prelude::*;
-use gtk4::{Box as GtkBox, Image, Label, Button, Orientation, Widget};
+use gtk4::{Align, Box as GtkBox, Image, Label, Button, Orientation, Separator, Widget};
+use std::cell::Cell;
+use crate::parser::geometry::Length;
 use crate::parser::html_parser::DomNode;
-
+use super::access::{check_access, widget_dir};
+use super::actions::event_handler;
+use super::attributes::BoxAttributes;
+use super::flex::{flex_items, render_flex, FlexItem};
+use super::form::render_form;
+use super::grid::{render_cell, render_grid, render_table};
+use super::inputs::{mark_submit_button, render_input, render_select, render_textarea};
+use super::layout::apply_layout_style;
+use super::overlay::render_overlay;
+use super::stylesheet::inline_style_class;
+use super::inline::{
+    element_label, group_inline, inline_label, inline_markup, is_inline, is_styled_inline, preformatted_label, Flow,
+};
+
+/// Viñetas de `<ul>` según el nivel de anidamiento.
+const BULLETS: &[&str] = &["•", "◦", "▪"];
+
+/// Crea el widget GTK de un nodo y le aplica `id`, `class`, `style` y los
+/// atributos de tamaño y colocación.
 pub fn render_dom_to_gtk(node: &DomNode) -> Widget {
+    finish_element(render_element(node), node)
+}
+
+// El `style` va después que los atributos para que tenga prioridad, como en HTML
+fn finish_element(widget: Widget, node: &DomNode) -> Widget {
+    apply_attributes(&widget, node);
+    let widget = BoxAttributes::from_node(node).apply(widget);
+    apply_layout_style(widget, node)
+}
+
+// `id` pasa a ser el nombre del widget (`#id` en CSS), `class` sus clases CSS
+// y `style` una clase generada cuyas reglas carga el `ScopedStyle` de la ventana
+fn apply_attributes(widget: &Widget, node: &DomNode) {
+    if let Some(id) = node.attributes.get("id") {
+        widget.set_widget_name(id);
+    }
+
+    if let Some(classes) = node.attributes.get("class") {
+        for class in classes.split_whitespace() {
+            widget.add_css_class(class);
+        }
+    }
+
+    if let Some(style) = node.attributes.get("style").filter(|style| !style.trim().is_empty()) {
+        widget.add_css_class(&inline_style_class(style));
+    }
+}
+
+fn render_element(node: &DomNode) -> Widget {
     match node.tag_name.as_str() {
         "text" => {
-            if let Some(text) = &node.text_content {
-                let lbl = Label::new(Some(text));
-                lbl.set_wrap(true);
-                return lbl.upcast();
-            }
-            return Label::new(None).upcast();
+            return inline_label(&[node]).upcast();
         }
 
-        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
-            let container = GtkBox::new(Orientation::Vertical, 0);
+        // El tamaño y el peso salen de la hoja de estilos por defecto (`h1 { ... }`)
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "p" => {
+            return render_flex(node, flex_items(node), 0);
+        }
 
-            for child in &node.children {
-                let widget = render_dom_to_gtk(child);
+        "pre" => {
+            let label = preformatted_label(node);
+            label.add_css_class("pre");
 
-                if let Some(label) = widget.downcast_ref::<Label>() {
-                    let size = match node.tag_name.as_str() {
-                        "h1" => 32,
-                        "h2" => 28,
-                        "h3" => 24,
-                        "h4" => 20,
-                        "h5" => 18,
-                        _ => 16,
-                    };
+            return label.upcast();
+        }
 
-                    let markup = format!("<span size='{}000'><b>{}</b></span>", size, label.text());
-                    label.set_markup(&markup);
-                }
+        "hr" => {
+            let separator = Separator::builder()
+                .css_name("hr")
+                .orientation(Orientation::Horizontal)
+                .build();
+            separator.set_margin_top(6);
+            separator.set_margin_bottom(6);
+            separator.add_css_class("hr");
 
-                container.append(&widget);
-            }
-
-            return container.upcast();
+            return separator.upcast();
         }
 
-        "p" => {
-            let container = GtkBox::new(Orientation::Vertical, 0);
+        "blockquote" => {
+            let container = render_flex(node, flex_items(node), 6);
+            container.set_margin_start(20);
+            container.add_css_class("blockquote");
 
-            for child in &node.children {
-                container.append(&render_dom_to_gtk(child));
-            }
+            return container;
+        }
+
+        "ul" | "ol" => {
+            return render_list(node, 0);
+        }
 
-            return container.upcast();
+        "li" => {
+            return render_list_item(node, BULLETS[0], 0);
         }
 
         "img" => {
             if let Some(src) = node.attributes.get("src") {
-                let exe_dir = std::env::current_exe()
-                    .unwrap()
-                    .parent()
-                    .unwrap()
-                    .to_path_buf();
-
-                let widget_dir = exe_dir.join("widget");
-
-                let img_path = widget_dir.join(src);
+                let img = Image::builder().css_name("img").build();
 
-                let img = Image::from_file(&img_path);
-
-                let size = node.attributes
-                    .get("width")
-                    .and_then(|w| w.parse::<i32>().ok())
-                    .unwrap_or(350);
+                // `width` en píxeles es también el tamaño del icono
+                let size = match node.attributes.get("width").and_then(|w| w.parse().ok()) {
+                    Some(px @ Length::Px(_)) => px.resolve(0),
+                    _ => 350,
+                };
 
                 img.set_pixel_size(size);
 
+                // La ruta es relativa al archivo del widget, que solo se conoce
+                // cuando la imagen ya está dentro de su ventana
+                let src = src.clone();
+                let loaded = Cell::new(false);
+                img.connect_realize(move |img| {
+                    if !loaded.replace(true) {
+                        load_image(img, &src);
+                    }
+                });
+
                 return img.upcast();
             }
 
@@ -78,30 +127,60 @@ pub fn render_dom_to_gtk(node: &DomNode) -> Widget {
         }
 
 
+        // Texto en línea con `id`, `class` o `style`: su propio `Label`
+        _ if is_styled_inline(node) => {
+            return element_label(&node.tag_name, &inline_markup(&[node])).upcast();
+        }
+
+        // Contenedores flexibles: columna sin márgenes ni separación por defecto
         "div" | "body" | "id" | "span" => {
-            let container = GtkBox::new(Orientation::Vertical, 10);
-            container.set_margin_start(10);
-            container.set_margin_end(10);
-            container.set_margin_top(10);
-            container.set_margin_bottom(10);
+            let mut items = flex_items(node);
 
             if node.children.is_empty() {
                 if let Some(id) = node.attributes.get("id") {
                     let debug_label = Label::new(Some(&format!("Contenedor: {}", id)));
-                    container.append(&debug_label);
+                    items.push(FlexItem { widget: debug_label.upcast(), node: None });
                 }
             }
 
-            for child in &node.children {
-                container.append(&render_dom_to_gtk(child));
-            }
+            return render_flex(node, items, 0);
+        }
+
+        "overlay" => {
+            return render_overlay(node);
+        }
+
+        "form" => {
+            return render_form(node);
+        }
+
+        "input" | "checkbox" | "range" => {
+            return render_input(node);
+        }
+
+        "textarea" => {
+            return render_textarea(node);
+        }
+
+        "select" => {
+            return render_select(node);
+        }
+
+        "grid" => {
+            return render_grid(node);
+        }
+
+        "table" => {
+            return render_table(node);
+        }
 
-            return container.upcast();
+        "td" | "th" => {
+            return render_cell(node);
         }
 
         "button"=>{
            let button_text = if let Some(text_child) = node.children.first() {
-            text_child.text_content.clone().unwrap_or_else(|| "Button".to_string())
+            text_child.text_content.as_deref().map(str::trim).unwrap_or("Button").to_string()
             } else {
                 node.attributes.get("value")
                     .cloned()
@@ -110,61 +189,129 @@ pub fn render_dom_to_gtk(node: &DomNode) -> Widget {
             };
 
             let button = Button::with_label(&button_text);
+            mark_submit_button(&button, node);
 
-            if let Some(width) = node.attributes.get("width") {
-                if let Ok(w) = width.parse::<i32>() {
-                    button.set_width_request(w);
-                }
-            }
-
-            if let Some(height) = node.attributes.get("height") {
-                if let Ok(h) = height.parse::<i32>() {
-                    button.set_height_request(h);
-                }
-            }
-
-            if let Some(id) = node.attributes.get("id") {
-                button.set_widget_name(id);
-            }
-
-            if let Some(onclick_attr) = node.attributes.get("onclick") {
-                let accion = if onclick_attr.trim().is_empty() {
-                    if let Some(id) = node.attributes.get("id") {
-                        format!("Button '{}' clicked", id)
-                    } else {
-                        "Button clicked".to_string()
+            // Sin `onclick` (o vacío) se escribe qué botón se pulsó
+            match node.attributes.get("onclick").filter(|onclick| !onclick.trim().is_empty()) {
+                Some(_) => {
+                    if let Some(actions) = event_handler(node, "onclick") {
+                        button.connect_clicked(move |button| actions.run(button.upcast_ref()));
                     }
-                } else {
-                    onclick_attr.clone()
-                };
-
-                button.connect_clicked(move |_| {
-                    println!("{}", accion);
-                });
-            } else {
-                if let Some(id) = node.attributes.get("id") {
-                    let msg = format!("Button '{}' clicked", id);
+                }
+                None => {
+                    let msg = match node.attributes.get("id") {
+                        Some(id) => format!("Button '{}' clicked", id),
+                        None => "Button clicked".to_string(),
+                    };
                     button.connect_clicked(move |_| println!("{}", msg));
-                } else {
-                    button.connect_clicked(|_| println!("Button clicked"));
                 }
             }
 
             return button.upcast();
 
         }
-        _ => {
-            let container = GtkBox::new(Orientation::Vertical, 6);
+        _ if is_inline(node) => {
+            return element_label(&node.tag_name, &inline_markup(&[node])).upcast();
+        }
 
+        _ => {
             let debug_label = Label::new(Some(&format!("⚠️ Tag no soportado: <{}>", node.tag_name)));
             debug_label.set_opacity(0.5);
-            container.append(&debug_label);
 
-            for child in &node.children {
-                container.append(&render_dom_to_gtk(child));
+            let mut items = vec![FlexItem { widget: debug_label.upcast(), node: None }];
+            items.extend(flex_items(node));
+
+            return render_flex(node, items, 0);
+        }
+    }
+}
+
+// Fuera de la carpeta del widget hace falta permiso de lectura
+fn load_image(img: &Image, src: &str) {
+    let Some(dir) = widget_dir(img.upcast_ref()) else {
+        eprintln!("  ✗ No se puede cargar la imagen '{}': el widget no tiene archivo", src);
+        return;
+    };
+
+    let path = dir.join(src);
+    if check_access(img.upcast_ref(), |permissions, base| permissions.check_read(&path, base)) {
+        img.set_from_file(Some(&path));
+    }
+}
+
+// Contenedor cuyo nodo CSS se llama como la etiqueta, para que `ul { ... }` o
+// `li:hover` funcionen en las hojas de estilos
+fn element_box(tag_name: &str, orientation: Orientation, spacing: i32) -> GtkBox {
+    GtkBox::builder()
+        .css_name(tag_name)
+        .orientation(orientation)
+        .spacing(spacing)
+        .build()
+}
+
+fn render_list(list: &DomNode, depth: usize) -> Widget {
+    let container = element_box(&list.tag_name, Orientation::Vertical, 2);
+    container.add_css_class(&list.tag_name);
+
+    let ordered = list.tag_name == "ol";
+    let mut number = list
+        .attributes
+        .get("start")
+        .and_then(|start| start.trim().parse::<i64>().ok())
+        .unwrap_or(1);
+
+    for item in group_inline(&list.children) {
+        match item {
+            Flow::Block(child) if child.tag_name == "li" => {
+                // `<li value="n">` reinicia la numeración como en HTML
+                if let Some(value) = child.attributes.get("value").and_then(|v| v.trim().parse().ok()) {
+                    number = value;
+                }
+
+                let marker = if ordered {
+                    format!("{}.", number)
+                } else {
+                    BULLETS[depth % BULLETS.len()].to_string()
+                };
+                number += 1;
+
+                container.append(&finish_element(render_list_item(child, &marker, depth), child));
             }
+            Flow::Block(child) => container.append(&render_dom_to_gtk(child)),
+            Flow::Inline(nodes) => container.append(&inline_label(&nodes)),
+        }
+    }
+
+    container.upcast()
+}
+
+// Marcador a la izquierda y contenido alineado; las listas anidadas cambian de viñeta
+fn render_list_item(item: &DomNode, marker: &str, depth: usize) -> Widget {
+    let row = element_box("li", Orientation::Horizontal, 6);
+    row.add_css_class("li");
 
-            return container.upcast();
+    let marker = Label::new(Some(marker));
+    marker.add_css_class("marker");
+    marker.set_valign(Align::Start);
+    row.append(&marker);
+
+    let content = GtkBox::new(Orientation::Vertical, 2);
+    content.set_hexpand(true);
+
+    for flow in group_inline(&item.children) {
+        match flow {
+            Flow::Inline(nodes) => {
+                let label = inline_label(&nodes);
+                label.set_xalign(0.0);
+                content.append(&label);
+            }
+            Flow::Block(child) if child.tag_name == "ul" || child.tag_name == "ol" => {
+                content.append(&finish_element(render_list(child, depth + 1), child));
+            }
+            Flow::Block(child) => content.append(&render_dom_to_gtk(child)),
         }
     }
-}
\ No newline at end of file
+
+    row.append(&content);
+    row.upcast()
+}
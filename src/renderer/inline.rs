@@ -0,0 +1,342 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib, Label};
+
+use super::access::check_access;
+use crate::parser::html_parser::DomNode;
+
+/// Etiquetas de formato en línea que se convierten en markup de Pango.
+const INLINE_TAGS: &[&str] = &[
+    "text", "b", "strong", "i", "em", "u", "s", "del", "code", "kbd", "a", "br", "span",
+    "small", "big", "sub", "sup",
+];
+
+/// Elementos que van en línea pero son un widget propio y no parte del `Label`.
+const ATOMIC_INLINE_TAGS: &[&str] = &["img", "button", "input", "checkbox", "range", "select", "textarea"];
+
+/// Atributos que el markup de Pango no puede llevar: con ellos un elemento de
+/// texto en línea pasa a ser un `Label` propio.
+const OWN_ATTRIBUTES: &[&str] = &["id", "class", "style"];
+
+/// Contenido de un contenedor agrupado en tramos de texto en línea y bloques.
+pub enum Flow<'a> {
+    Inline(Vec<&'a DomNode>),
+    Block(&'a DomNode),
+}
+
+/// Un nodo es en línea si su etiqueta lo es y todo su contenido también. Los
+/// que tienen `id`, `class` o `style` no, ver `is_styled_inline`.
+pub fn is_inline(node: &DomNode) -> bool {
+    INLINE_TAGS.contains(&node.tag_name.as_str())
+        && !is_absolute(node)
+        && !has_own_attributes(node)
+        && node.children.iter().all(is_inline)
+}
+
+/// Elemento de texto en línea (`<span class="...">`, `<b style="...">`) con
+/// atributos propios. Es un `Label` aparte, para que se le apliquen, que va
+/// en la misma línea que el texto de alrededor.
+pub fn is_styled_inline(node: &DomNode) -> bool {
+    INLINE_TAGS.contains(&node.tag_name.as_str())
+        && !is_absolute(node)
+        && has_own_attributes(node)
+        && node.children.iter().all(is_inline)
+}
+
+fn has_own_attributes(node: &DomNode) -> bool {
+    node.tag_name != "text" && OWN_ATTRIBUTES.iter().any(|name| node.attributes.contains_key(*name))
+}
+
+/// Si el elemento va en una línea de texto: los de `is_inline` y los widgets
+/// en línea como `<img>` o `<button>`, con lo que tengan dentro.
+pub fn is_inline_level(node: &DomNode) -> bool {
+    let tag = node.tag_name.as_str();
+    (INLINE_TAGS.contains(&tag) || ATOMIC_INLINE_TAGS.contains(&tag)) && !is_absolute(node)
+}
+
+/// Como en CSS, un elemento con `position="absolute"` deja de ir en línea.
+pub fn is_absolute(node: &DomNode) -> bool {
+    node.attributes.get("position").is_some_and(|position| position.trim() == "absolute")
+}
+
+/// Agrupa los hijos consecutivos en línea para renderizarlos como un único `Label`.
+pub fn group_inline(children: &[DomNode]) -> Vec<Flow<'_>> {
+    let mut flow = Vec::new();
+    let mut run = Vec::new();
+
+    for child in children {
+        // Las hojas de estilo del widget no se muestran ni cortan el texto
+        if child.tag_name == "style" || child.tag_name == "link" {
+            continue;
+        }
+        if is_inline(child) {
+            run.push(child);
+            continue;
+        }
+
+        if !run.is_empty() {
+            flow.push(Flow::Inline(std::mem::take(&mut run)));
+        }
+        flow.push(Flow::Block(child));
+    }
+
+    if !run.is_empty() {
+        flow.push(Flow::Inline(run));
+    }
+
+    // Los tramos que solo tienen espacios (sangría entre bloques) no se muestran
+    flow.retain(|item| match item {
+        Flow::Inline(nodes) => !inline_markup(nodes).is_empty(),
+        Flow::Block(_) => true,
+    });
+
+    flow
+}
+
+/// Markup de Pango de un tramo en línea, con el texto escapado y los espacios
+/// colapsados como en HTML.
+pub fn inline_markup(nodes: &[&DomNode]) -> String {
+    let mut markup = String::new();
+    let mut last_was_space = true;
+
+    for node in nodes {
+        write_markup(node, &mut markup, &mut last_was_space, true);
+    }
+
+    if markup.ends_with(' ') {
+        markup.pop();
+    }
+    markup
+}
+
+/// Parte el markup de un tramo en palabras, cada una con las etiquetas que
+/// la envuelven cerradas, para que un contexto en línea pueda pasar de línea
+/// entre ellas. Un `<br>` es la entrada `"\n"`.
+pub fn markup_words(markup: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    // Etiquetas abiertas, con sus atributos, para volver a abrirlas en la siguiente palabra
+    let mut open: Vec<&str> = Vec::new();
+    let mut word = String::new();
+    let mut has_text = false;
+    let mut rest = markup;
+
+    while let Some(c) = rest.chars().next() {
+        match c {
+            '<' => {
+                let end = rest.find('>').map_or(rest.len(), |end| end + 1);
+                let tag = &rest[..end];
+                if tag.starts_with("</") {
+                    open.pop();
+                } else if !tag.ends_with("/>") {
+                    open.push(tag);
+                }
+                word.push_str(tag);
+                rest = &rest[end..];
+                continue;
+            }
+            ' ' | '\n' if has_text => {
+                finish_word(&mut words, &mut word, &open, c == '\n');
+                has_text = false;
+            }
+            ' ' => {}
+            '\n' => words.push("\n".to_string()),
+            _ => {
+                word.push(c);
+                has_text = true;
+            }
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    if has_text {
+        finish_word(&mut words, &mut word, &open, false);
+    }
+    words
+}
+
+// Cierra las etiquetas abiertas y empieza la palabra siguiente abriéndolas
+fn finish_word(words: &mut Vec<String>, word: &mut String, open: &[&str], line_break: bool) {
+    for tag in open.iter().rev() {
+        let name = tag[1..].split(|c: char| c.is_whitespace() || c == '>').next().unwrap_or("");
+        word.push_str(&format!("</{}>", name));
+    }
+    words.push(std::mem::replace(word, open.concat()));
+    if line_break {
+        words.push("\n".to_string());
+    }
+}
+
+/// `Label` con el markup del tramo; los enlaces `<a href>` se abren con la
+/// aplicación por defecto.
+pub fn inline_label(nodes: &[&DomNode]) -> Label {
+    element_label("label", &inline_markup(nodes))
+}
+
+/// `Label` monoespaciado con el contenido de `<pre>`, conservando los espacios
+/// y saltos de línea.
+pub fn preformatted_label(node: &DomNode) -> Label {
+    let mut markup = String::new();
+    let mut last_was_space = false;
+
+    for child in &node.children {
+        write_markup(child, &mut markup, &mut last_was_space, false);
+    }
+
+    let label = element_label("pre", &markup);
+    label.set_wrap(false);
+    label.set_xalign(0.0);
+    label.add_css_class("monospace");
+    label
+}
+
+/// `Label` con markup cuyo nodo CSS se llama `css_name`, para que lo
+/// seleccione la etiqueta YTML que lo generó (el texto suelto es `label`).
+pub fn element_label(css_name: &str, markup: &str) -> Label {
+    let label = Label::builder().css_name(css_name).build();
+    label.set_markup(markup);
+    label.set_wrap(true);
+
+    label.connect_activate_link(|label, uri| {
+        if !check_access(label.upcast_ref(), |permissions, base| permissions.check_uri(uri, base)) {
+            return glib::Propagation::Stop;
+        }
+        if let Err(e) = gio::AppInfo::launch_default_for_uri(uri, gio::AppLaunchContext::NONE) {
+            eprintln!("✗ No se pudo abrir el enlace {}: {}", uri, e);
+        }
+        glib::Propagation::Stop
+    });
+
+    label
+}
+
+// `collapse` colapsa los espacios como en HTML; en `<pre>` se escriben tal cual
+fn write_markup(node: &DomNode, markup: &mut String, last_was_space: &mut bool, collapse: bool) {
+    if node.tag_name == "text" {
+        for c in node.text_content.as_deref().unwrap_or("").chars() {
+            if collapse && c.is_whitespace() {
+                if !*last_was_space {
+                    markup.push(' ');
+                }
+                *last_was_space = true;
+            } else {
+                push_escaped(markup, c);
+                *last_was_space = false;
+            }
+        }
+        return;
+    }
+
+    if node.tag_name == "br" {
+        if collapse && markup.ends_with(' ') {
+            markup.pop();
+        }
+        markup.push('\n');
+        *last_was_space = true;
+        return;
+    }
+
+    let (open, close) = match node.tag_name.as_str() {
+        "b" | "strong" => ("<b>".to_string(), "</b>"),
+        "i" | "em" => ("<i>".to_string(), "</i>"),
+        "u" => ("<u>".to_string(), "</u>"),
+        "s" | "del" => ("<s>".to_string(), "</s>"),
+        "code" | "kbd" => ("<tt>".to_string(), "</tt>"),
+        "small" => ("<small>".to_string(), "</small>"),
+        "big" => ("<big>".to_string(), "</big>"),
+        "sub" => ("<sub>".to_string(), "</sub>"),
+        "sup" => ("<sup>".to_string(), "</sup>"),
+        "a" => {
+            let href = node.attributes.get("href").map(String::as_str).unwrap_or("");
+            let open = match node.attributes.get("title") {
+                Some(title) => format!(
+                    "<a href=\"{}\" title=\"{}\">",
+                    glib::markup_escape_text(href),
+                    glib::markup_escape_text(title)
+                ),
+                None => format!("<a href=\"{}\">", glib::markup_escape_text(href)),
+            };
+            (open, "</a>")
+        }
+        _ => (String::new(), ""),
+    };
+
+    markup.push_str(&open);
+    for child in &node.children {
+        write_markup(child, markup, last_was_space, collapse);
+    }
+    markup.push_str(close);
+}
+
+fn push_escaped(markup: &mut String, c: char) {
+    match c {
+        '&' => markup.push_str("&amp;"),
+        '<' => markup.push_str("&lt;"),
+        '>' => markup.push_str("&gt;"),
+        '"' => markup.push_str("&quot;"),
+        '\'' => markup.push_str("&#39;"),
+        _ => markup.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn element(tag: &str, attributes: &[(&str, &str)], children: Vec<DomNode>) -> DomNode {
+        DomNode {
+            tag_name: tag.to_string(),
+            attributes: attributes.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            children,
+            ..Default::default()
+        }
+    }
+
+    fn text(content: &str) -> DomNode {
+        DomNode {
+            tag_name: "text".to_string(),
+            text_content: Some(content.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn styled_inline_elements_are_not_merged_into_the_text() {
+        let paragraph = element(
+            "p",
+            &[],
+            vec![
+                text("Hola "),
+                element("b", &[], vec![text("mundo")]),
+                text(" y "),
+                element("span", &[("id", "x"), ("class", "y"), ("style", "color: red")], vec![text("más")]),
+            ],
+        );
+
+        let flow = group_inline(&paragraph.children);
+        let [Flow::Inline(nodes), Flow::Block(span)] = flow.as_slice() else {
+            panic!("se esperaba un tramo de texto y el <span> aparte");
+        };
+        assert_eq!(inline_markup(nodes), "Hola <b>mundo</b> y");
+        assert!(is_styled_inline(span) && is_inline_level(span) && !is_inline(span));
+        assert_eq!(inline_markup(&[*span]), "más");
+
+        // Con contenido que no va en línea sigue siendo un contenedor
+        let block = element("span", &[("class", "y")], vec![element("div", &[], vec![])]);
+        assert!(!is_styled_inline(&block));
+    }
+
+    #[test]
+    fn markup_is_split_into_words_with_their_tags() {
+        assert_eq!(
+            markup_words("Abre <a href=\"https://x.org\" title=\"la web\">la <b>web</b></a> ya\ny"),
+            [
+                "Abre",
+                "<a href=\"https://x.org\" title=\"la web\">la</a>",
+                "<a href=\"https://x.org\" title=\"la web\"><b>web</b></a>",
+                "ya",
+                "\n",
+                "y",
+            ]
+        );
+        assert_eq!(markup_words("a&amp;b <i>c</i>d"), ["a&amp;b", "<i>c</i>d"]);
+    }
+}
@@ -0,0 +1,290 @@
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{Label, Orientation, SizeRequestMode, Widget};
+use std::cell::Cell;
+
+use super::css::parse_declarations;
+use super::flex::{gaps, FlexItem};
+use super::inline::{element_label, is_absolute, is_inline, is_inline_level, markup_words};
+use crate::parser::html_parser::DomNode;
+
+/// Separación por defecto entre piezas de una línea, más o menos un espacio.
+const INLINE_SPACING: i32 = 4;
+
+/// Nodo CSS de las piezas que terminan una línea (`<br>`).
+const LINE_BREAK: &str = "br";
+
+/// Atributos y propiedades que piden un contenedor flexible explícitamente.
+const FLEX_SETTINGS: &[&str] = &[
+    "direction", "flex-direction", "wrap", "flex-wrap", "align", "align-items", "justify", "justify-content",
+];
+
+/// Un contenedor abre un contexto en línea, como en HTML, si todos sus hijos
+/// van en línea y alguno es un widget propio (`<img>`, `<button>`...); si
+/// solo hay texto basta con un `Label`. `direction`, `wrap`, `align` o
+/// `justify` (o su CSS) lo convierten en un contenedor flexible.
+pub fn has_inline_flow(node: &DomNode) -> bool {
+    let mut children = node
+        .children
+        .iter()
+        .filter(|child| child.tag_name != "style" && child.tag_name != "link" && !is_absolute(child))
+        .peekable();
+
+    let flex_style = node
+        .attributes
+        .get("style")
+        .map(|style| parse_declarations(style))
+        .unwrap_or_default()
+        .iter()
+        .any(|declaration| FLEX_SETTINGS.contains(&declaration.name.as_str()));
+    let flex_attribute = FLEX_SETTINGS.iter().any(|name| node.attributes.contains_key(*name));
+
+    children.peek().is_some()
+        && !flex_style
+        && !flex_attribute
+        && node.children.iter().any(|child| !is_inline(child) && is_inline_level(child))
+        && children.all(is_inline_level)
+}
+
+/// Contenedor con nodo CSS `css_name` que coloca los hijos de izquierda a
+/// derecha alineados por la línea base del texto y pasa a la línea siguiente
+/// al llegar al ancho del contenedor. Los tramos de texto se parten en una
+/// pieza por palabra, así que el texto pasa de línea alrededor de las imágenes
+/// y botones como en HTML. `gap` separa las piezas y las líneas; `text-align`
+/// reparte el espacio que sobra en cada línea.
+pub fn render_inline_flow(css_name: &str, node: &DomNode, items: Vec<FlexItem>) -> Widget {
+    let (row_spacing, column_spacing) = gaps(node, INLINE_SPACING);
+
+    let layout: InlineLayout = glib::Object::new();
+    layout.imp().row_spacing.set(row_spacing);
+    layout.imp().column_spacing.set(column_spacing);
+    layout.imp().xalign.set(text_align(node));
+
+    let container: Widget = glib::Object::builder::<InlineBox>()
+        .property("css-name", css_name)
+        .property("layout-manager", &layout)
+        .build()
+        .upcast();
+    for item in items {
+        let text = item.node.is_none().then(|| item.widget.downcast_ref::<Label>().map(Label::label)).flatten();
+        match text {
+            Some(markup) => {
+                for word in markup_words(&markup) {
+                    // `<br>` es una pieza vacía que termina la línea
+                    let piece = match word.as_str() {
+                        "\n" => element_label(LINE_BREAK, ""),
+                        _ => element_label("label", &word),
+                    };
+                    piece.set_wrap(false);
+                    piece.set_parent(&container);
+                }
+            }
+            None => item.widget.set_parent(&container),
+        }
+    }
+
+    container
+}
+
+// Posición de cada línea: 0 a la izquierda, 0.5 centrada y 1 a la derecha
+fn text_align(node: &DomNode) -> f32 {
+    let style = node.attributes.get("style").map(|style| parse_declarations(style)).unwrap_or_default();
+    match style.iter().rev().find(|declaration| declaration.name == "text-align") {
+        Some(declaration) => match declaration.value.as_str() {
+            "center" => 0.5,
+            "right" | "end" => 1.0,
+            _ => 0.0,
+        },
+        None => 0.0,
+    }
+}
+
+/// Hijo ya medido dentro de una línea.
+struct Piece {
+    widget: Widget,
+    width: i32,
+    height: i32,
+    /// Distancia desde arriba hasta la línea base, o `None` si el widget no
+    /// tiene texto (una imagen se apoya en la línea base por abajo).
+    baseline: Option<i32>,
+}
+
+impl Piece {
+    fn ascent(&self) -> i32 {
+        self.baseline.unwrap_or(self.height)
+    }
+}
+
+#[derive(Default)]
+struct Line {
+    pieces: Vec<Piece>,
+    width: i32,
+    ascent: i32,
+    descent: i32,
+}
+
+impl Line {
+    fn height(&self) -> i32 {
+        self.ascent + self.descent
+    }
+}
+
+mod imp {
+    use super::*;
+    use gtk4::glib;
+
+    #[derive(Default)]
+    pub struct InlineLayout {
+        pub row_spacing: Cell<i32>,
+        pub column_spacing: Cell<i32>,
+        pub xalign: Cell<f32>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for InlineLayout {
+        const NAME: &'static str = "YtmlInlineLayout";
+        type Type = super::InlineLayout;
+        type ParentType = gtk4::LayoutManager;
+    }
+
+    impl ObjectImpl for InlineLayout {}
+
+    impl LayoutManagerImpl for InlineLayout {
+        fn request_mode(&self, _widget: &Widget) -> SizeRequestMode {
+            SizeRequestMode::HeightForWidth
+        }
+
+        // Lo más estrecho es la pieza más ancha; lo natural, todo en una línea
+        fn measure(&self, widget: &Widget, orientation: Orientation, for_size: i32) -> (i32, i32, i32, i32) {
+            match orientation {
+                Orientation::Horizontal => {
+                    let minimum = children(widget)
+                        .map(|child| child.measure(Orientation::Horizontal, -1).0)
+                        .max()
+                        .unwrap_or(0);
+                    let natural = self.lines(widget, None).first().map_or(0, |line| line.width);
+                    (minimum, natural.max(minimum), -1, -1)
+                }
+                _ => {
+                    let lines = self.lines(widget, (for_size >= 0).then_some(for_size));
+                    let height = self.height(&lines);
+                    let baseline = lines.first().map_or(-1, |line| line.ascent);
+                    (height, height, baseline, baseline)
+                }
+            }
+        }
+
+        fn allocate(&self, widget: &Widget, width: i32, _height: i32, _baseline: i32) {
+            let mut y = 0;
+            for line in self.lines(widget, Some(width)) {
+                let mut x = ((width - line.width).max(0) as f32 * self.xalign.get()) as i32;
+
+                for piece in &line.pieces {
+                    let top = y + line.ascent - piece.ascent();
+                    piece.widget.size_allocate(
+                        &gtk4::Allocation::new(x, top, piece.width, piece.height),
+                        piece.baseline.unwrap_or(-1),
+                    );
+                    x += piece.width + self.column_spacing.get();
+                }
+
+                y += line.height() + self.row_spacing.get();
+            }
+        }
+    }
+
+    impl InlineLayout {
+        // Reparte los hijos en líneas de como mucho `width` píxeles; sin
+        // ancho todo va en una sola línea
+        fn lines(&self, widget: &Widget, width: Option<i32>) -> Vec<Line> {
+            let spacing = self.column_spacing.get();
+            let mut lines = Vec::new();
+            let mut line = Line::default();
+
+            for child in children(widget) {
+                let line_break = child.css_name() == LINE_BREAK;
+                let (min_width, natural_width, ..) = child.measure(Orientation::Horizontal, -1);
+                let child_width = match line_break {
+                    true => 0,
+                    false => width.map_or(natural_width, |width| natural_width.min(width)).max(min_width),
+                };
+
+                let needed = if line.pieces.is_empty() { child_width } else { line.width + spacing + child_width };
+                if !line.pieces.is_empty() && !line_break && width.is_some_and(|width| needed > width) {
+                    lines.push(std::mem::take(&mut line));
+                }
+
+                let (_, height, _, baseline) = child.measure(Orientation::Vertical, child_width);
+                let piece = Piece {
+                    widget: child,
+                    width: child_width,
+                    height,
+                    baseline: (baseline >= 0).then_some(baseline),
+                };
+
+                if !line.pieces.is_empty() && !line_break {
+                    line.width += spacing;
+                }
+                line.width += piece.width;
+                line.ascent = line.ascent.max(piece.ascent());
+                line.descent = line.descent.max(piece.height - piece.ascent());
+                line.pieces.push(piece);
+
+                // Aunque la línea esté vacía ocupa lo que una línea de texto
+                if line_break {
+                    lines.push(std::mem::take(&mut line));
+                }
+            }
+
+            if !line.pieces.is_empty() {
+                lines.push(line);
+            }
+            lines
+        }
+
+        fn height(&self, lines: &[Line]) -> i32 {
+            let spacing = self.row_spacing.get() * (lines.len() as i32 - 1).max(0);
+            lines.iter().map(Line::height).sum::<i32>() + spacing
+        }
+    }
+
+    fn children(widget: &Widget) -> impl Iterator<Item = Widget> {
+        std::iter::successors(widget.first_child(), |child| child.next_sibling())
+            .filter(|child| child.should_layout())
+    }
+
+    #[derive(Default)]
+    pub struct InlineBox;
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for InlineBox {
+        const NAME: &'static str = "YtmlInlineBox";
+        type Type = super::InlineBox;
+        type ParentType = Widget;
+    }
+
+    impl ObjectImpl for InlineBox {
+        fn dispose(&self) {
+            while let Some(child) = self.obj().first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for InlineBox {}
+}
+
+glib::wrapper! {
+    /// Gestor de disposición de un contexto en línea: piezas de izquierda a
+    /// derecha, alineadas por la línea base y en varias líneas si no caben.
+    pub struct InlineLayout(ObjectSubclass<imp::InlineLayout>)
+        @extends gtk4::LayoutManager;
+}
+
+glib::wrapper! {
+    /// Contenedor sin disposición propia para usarlo con `InlineLayout`.
+    pub struct InlineBox(ObjectSubclass<imp::InlineBox>)
+        @extends Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
@@ -0,0 +1,653 @@
+use gtk4::prelude::*;
+use gtk4::{
+    gdk, glib, Button, Calendar, CheckButton, ClosureExpression, ColorDialog, ColorDialogButton, DropDown, Editable, Entry,
+    Expression, Label, MenuButton, Orientation, PasswordEntry, Popover, Scale, ScrolledWindow, SearchEntry,
+    SpinButton, StringList, StringObject, TextView, Widget, WrapMode,
+};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+
+use super::actions::event_handler;
+use super::attributes::{flag, number_attribute};
+use crate::parser::html_parser::DomNode;
+
+/// Clave con la que cada control guarda su `Input`.
+const INPUT_KEY: &str = "ytml-input";
+
+/// Tipos de `<input>` que son un campo de texto de una línea.
+const TEXT_TYPES: &[&str] = &["text", "email", "url", "tel"];
+
+thread_local! {
+    // Mientras `restore_inputs` pone los valores no se avisa de los cambios
+    static RESTORING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Clase de control de formulario.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputKind {
+    Text,
+    Number,
+    Checkbox,
+    /// Con el `value` que aporta cuando está marcado.
+    Radio(String),
+    Range,
+    Color,
+    Date,
+    TextArea,
+    Select,
+    /// Botón que envía su `<form>`; no tiene valor.
+    Submit,
+}
+
+/// Lo que GTK no guarda de un control: qué es, su `name` y cómo se valida.
+#[derive(Debug, Clone)]
+pub struct Input {
+    pub kind: InputKind,
+    pub name: Option<String>,
+    pub required: bool,
+    /// Expresión regular que debe cumplir todo el texto, si no está vacío.
+    pub pattern: Option<String>,
+}
+
+/// Valor actual de un control.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputValue {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl fmt::Display for InputValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputValue::Text(text) => f.write_str(text),
+            InputValue::Number(number) => write!(f, "{}", number),
+            InputValue::Bool(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// `<input type="...">`: `text` (por defecto), `password`, `search`,
+/// `number`, `checkbox`, `radio`, `range`, `color`, `date` o `submit`.
+/// `<checkbox>` y `<range>` son atajos de `<input type="checkbox">` y
+/// `<input type="range">`.
+pub fn render_input(node: &DomNode) -> Widget {
+    let input_type = match node.tag_name.as_str() {
+        "checkbox" | "range" => node.tag_name.clone(),
+        _ => node
+            .attributes
+            .get("type")
+            .map(|input_type| input_type.trim().to_ascii_lowercase())
+            .unwrap_or_else(|| "text".to_string()),
+    };
+
+    let (widget, kind) = match input_type.as_str() {
+        "password" => (editable_input(PasswordEntry::builder().show_peek_icon(true).build(), node), InputKind::Text),
+        "search" => (editable_input(SearchEntry::new(), node), InputKind::Text),
+        "number" => (number_input(node), InputKind::Number),
+        "checkbox" => (check_input(node, None), InputKind::Checkbox),
+        "radio" => {
+            let value = node.attributes.get("value").cloned().unwrap_or_else(|| "on".to_string());
+            (check_input(node, node.attributes.get("name")), InputKind::Radio(value))
+        }
+        "range" => (range_input(node), InputKind::Range),
+        "color" => (color_input(node), InputKind::Color),
+        "date" => (date_input(node), InputKind::Date),
+        "submit" => {
+            let label = node.attributes.get("value").map(String::as_str).unwrap_or("Submit");
+            (Button::with_label(label).upcast(), InputKind::Submit)
+        }
+        text if TEXT_TYPES.contains(&text) => (editable_input(Entry::new(), node), InputKind::Text),
+        other => {
+            eprintln!("  ⚠ <input type=\"{}\"> no está soportado, se usa un campo de texto", other);
+            (editable_input(Entry::new(), node), InputKind::Text)
+        }
+    };
+
+    finish_input(widget, node, kind)
+}
+
+/// `<textarea>`: texto de varias líneas. El valor inicial es su contenido o `value`.
+pub fn render_textarea(node: &DomNode) -> Widget {
+    let text_view = TextView::builder()
+        .wrap_mode(WrapMode::WordChar)
+        .accepts_tab(false)
+        .build();
+
+    let content: String = node.children.iter().filter_map(|child| child.text_content.as_deref()).collect();
+    let value = node.attributes.get("value").cloned().unwrap_or(content);
+    text_view.buffer().set_text(value.trim_start_matches('\n'));
+
+    // GtkTextView no tiene texto de ejemplo: se pone una etiqueta encima mientras está vacío
+    if let Some(placeholder) = node.attributes.get("placeholder") {
+        let label = Label::new(Some(placeholder));
+        label.add_css_class("dim-label");
+        label.set_can_target(false);
+        label.set_visible(text_view.buffer().char_count() == 0);
+        text_view.add_overlay(&label, 0, 0);
+
+        text_view.buffer().connect_changed(move |buffer| label.set_visible(buffer.char_count() == 0));
+    }
+
+    let scrolled = ScrolledWindow::builder()
+        .css_name("textarea")
+        .child(&text_view)
+        .hscrollbar_policy(gtk4::PolicyType::Never)
+        .min_content_height(60)
+        .propagate_natural_height(true)
+        .build();
+
+    finish_input(scrolled.upcast(), node, InputKind::TextArea)
+}
+
+/// `<select>` con sus `<option value="...">`; `selected` en una opción o
+/// `value` en el `<select>` eligen la inicial.
+pub fn render_select(node: &DomNode) -> Widget {
+    let options: Vec<&DomNode> = node
+        .children
+        .iter()
+        .flat_map(|child| match child.tag_name.as_str() {
+            "optgroup" => child.children.iter().collect(),
+            _ => vec![child],
+        })
+        .filter(|child| child.tag_name == "option")
+        .collect();
+
+    // Como en HTML, sin `value` la opción vale lo que su texto
+    let mut labels = HashMap::new();
+    let mut values = Vec::new();
+    for option in &options {
+        let label: String = option.children.iter().filter_map(|child| child.text_content.as_deref()).collect();
+        let label = label.trim().to_string();
+        let value = option.attributes.get("value").cloned().unwrap_or_else(|| label.clone());
+        labels.entry(value.clone()).or_insert(label);
+        values.push(value);
+    }
+
+    let model = StringList::new(&values.iter().map(String::as_str).collect::<Vec<_>>());
+    let expression = ClosureExpression::with_callback(Vec::<Expression>::new(), move |args| {
+        let value = args.first().and_then(|item| item.get::<StringObject>().ok()).map(|item| item.string());
+        value.and_then(|value| labels.get(value.as_str()).cloned()).unwrap_or_default()
+    });
+    let drop_down = DropDown::new(Some(model), Some(expression));
+
+    let selected = options
+        .iter()
+        .position(|option| flag(option, "selected"))
+        .or_else(|| {
+            let value = node.attributes.get("value")?;
+            values.iter().position(|option| option == value)
+        });
+    if let Some(selected) = selected {
+        drop_down.set_selected(selected as u32);
+    }
+
+    finish_input(drop_down.upcast(), node, InputKind::Select)
+}
+
+/// Marca un `<button>` como botón de envío de su `<form>`: como en HTML, lo
+/// son todos salvo `type="button"` y `type="reset"`.
+pub fn mark_submit_button(button: &Button, node: &DomNode) {
+    let button_type = node.attributes.get("type").map(|button_type| button_type.trim());
+    if !matches!(button_type, Some("button" | "reset")) {
+        attach_input(button.upcast_ref(), node, InputKind::Submit);
+    }
+}
+
+/// `Input` del widget, si es un control de formulario.
+pub fn input_of(widget: &Widget) -> Option<Input> {
+    // SAFETY: solo `attach_input` escribe esta clave, y siempre un `Input`
+    unsafe { widget.data::<Input>(INPUT_KEY).map(|input| input.as_ref().clone()) }
+}
+
+/// Valor actual de un control. Un `radio` sin marcar y una fecha sin elegir no tienen.
+pub fn input_value(widget: &Widget) -> Option<InputValue> {
+    match input_of(widget)?.kind {
+        InputKind::Text => {
+            let editable = widget.dynamic_cast_ref::<Editable>()?;
+            Some(InputValue::Text(editable.text().to_string()))
+        }
+        InputKind::Number => {
+            let spin = widget.downcast_ref::<SpinButton>()?;
+            Some(InputValue::Number(spin.value()))
+        }
+        InputKind::Checkbox => {
+            let check = widget.downcast_ref::<CheckButton>()?;
+            Some(InputValue::Bool(check.is_active()))
+        }
+        InputKind::Radio(value) => {
+            let check = widget.downcast_ref::<CheckButton>()?;
+            check.is_active().then_some(InputValue::Text(value))
+        }
+        InputKind::Range => {
+            let scale = widget.downcast_ref::<Scale>()?;
+            Some(InputValue::Number(scale.value()))
+        }
+        InputKind::Color => {
+            let rgba = widget.downcast_ref::<ColorDialogButton>()?.rgba();
+            let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            Some(InputValue::Text(format!(
+                "#{:02x}{:02x}{:02x}",
+                channel(rgba.red()),
+                channel(rgba.green()),
+                channel(rgba.blue())
+            )))
+        }
+        InputKind::Date => {
+            let label = widget.downcast_ref::<MenuButton>()?.label()?;
+            parse_date(&label).map(|_| InputValue::Text(label.to_string()))
+        }
+        InputKind::TextArea => {
+            let buffer = text_view(widget)?.buffer();
+            let (start, end) = buffer.bounds();
+            Some(InputValue::Text(buffer.text(&start, &end, false).to_string()))
+        }
+        InputKind::Select => {
+            let item = widget.downcast_ref::<DropDown>()?.selected_item()?;
+            Some(InputValue::Text(item.downcast::<StringObject>().ok()?.string().to_string()))
+        }
+        InputKind::Submit => None,
+    }
+}
+
+/// Llama a `callback` cada vez que el usuario cambia el valor del control.
+/// Los campos de texto de una línea avisan al pulsar Intro, como `change` en HTML.
+pub fn connect_input_changed(widget: &Widget, callback: impl Fn(&Widget) + 'static) {
+    let Some(input) = input_of(widget) else {
+        return;
+    };
+
+    let widget_ref = widget.clone();
+    let notify = move || {
+        if !RESTORING.get() {
+            callback(&widget_ref);
+        }
+    };
+
+    match input.kind {
+        InputKind::Text => {
+            widget.connect_local("activate", false, move |_| {
+                notify();
+                None
+            });
+        }
+        InputKind::Number => {
+            if let Some(spin) = widget.downcast_ref::<SpinButton>() {
+                spin.connect_value_changed(move |_| notify());
+            }
+        }
+        InputKind::Checkbox | InputKind::Radio(_) => {
+            if let Some(check) = widget.downcast_ref::<CheckButton>() {
+                let is_radio = matches!(input.kind, InputKind::Radio(_));
+                check.connect_toggled(move |check| {
+                    if !is_radio || check.is_active() {
+                        notify();
+                    }
+                });
+            }
+        }
+        InputKind::Range => {
+            if let Some(scale) = widget.downcast_ref::<Scale>() {
+                scale.connect_value_changed(move |_| notify());
+            }
+        }
+        InputKind::Color => {
+            if let Some(button) = widget.downcast_ref::<ColorDialogButton>() {
+                button.connect_rgba_notify(move |_| notify());
+            }
+        }
+        InputKind::Date => {
+            if let Some(button) = widget.downcast_ref::<MenuButton>() {
+                button.connect_label_notify(move |_| notify());
+            }
+        }
+        InputKind::TextArea => {
+            if let Some(text_view) = text_view(widget) {
+                text_view.buffer().connect_changed(move |_| notify());
+            }
+        }
+        InputKind::Select => {
+            if let Some(drop_down) = widget.downcast_ref::<DropDown>() {
+                drop_down.connect_selected_notify(move |_| notify());
+            }
+        }
+        InputKind::Submit => {}
+    }
+}
+
+/// Control con `id` igual a `id` en la misma ventana que `widget`.
+pub fn find_input(widget: &Widget, id: &str) -> Option<Widget> {
+    let root = widget.root()?;
+    find_descendant(root.upcast_ref(), &|child| child.widget_name() == id && input_of(child).is_some())
+}
+
+/// Controles dentro de `widget`, en orden de documento.
+pub fn inputs_in(widget: &Widget) -> Vec<Widget> {
+    let mut inputs = Vec::new();
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        // Los controles tienen botones y campos internos que no cuentan
+        if input_of(&current).is_some() {
+            inputs.push(current.clone());
+        } else {
+            inputs.extend(inputs_in(&current));
+        }
+        child = current.next_sibling();
+    }
+    inputs
+}
+
+/// Valores de los controles de `widget` que tienen `id` o `name`, para
+/// devolvérselos con `restore_inputs` a los de un nuevo render.
+pub fn save_inputs(widget: &Widget) -> HashMap<String, InputValue> {
+    inputs_in(widget)
+        .iter()
+        .filter_map(|input| Some((state_key(input)?, state_value(input)?)))
+        .collect()
+}
+
+/// Vuelve a poner en los controles de `widget` los valores que guardó
+/// `save_inputs`, sin lanzar sus `onchange`.
+pub fn restore_inputs(widget: &Widget, saved: &HashMap<String, InputValue>) {
+    RESTORING.set(true);
+    for input in inputs_in(widget) {
+        if let Some(value) = state_key(&input).and_then(|key| saved.get(&key)) {
+            set_input_value(&input, value);
+        }
+    }
+    RESTORING.set(false);
+}
+
+/// Valor de `{#id}` (el control con ese `id`) o `{$value}` (el propio
+/// `widget`), con la clave sin llaves; `None` si no es un control. Con `#` y
+/// `$` no se confunden con las props `{nombre}` de `<template>` e
+/// `<include>`, que se sustituyen antes, al cargar.
+pub fn input_placeholder(key: &str, widget: &Widget) -> Option<String> {
+    let target = match key {
+        "$value" => Some(widget.clone()),
+        key => key.strip_prefix('#').and_then(|id| find_input(widget, id)),
+    };
+    let target = target.filter(|target| input_of(target).is_some())?;
+    Some(input_value(&target).map(|value| value.to_string()).unwrap_or_default())
+}
+
+/// Recorre los `{...}` de `text`. `replace` recibe lo que hay entre llaves y
+/// el texto ya escrito, y devuelve por qué cambiarlo o `None` para dejarlo.
+pub fn replace_placeholders(text: &str, mut replace: impl FnMut(&str, &str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+
+        output.push_str(&rest[..start]);
+        match replace(&rest[start + 1..end], &output) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[start..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// `#id` o, sin id, el `name` (con el valor en los `radio`)
+fn state_key(widget: &Widget) -> Option<String> {
+    let input = input_of(widget)?;
+    let id = widget.widget_name();
+    // Sin nombre, GTK devuelve el del tipo
+    if id != widget.type_().name() {
+        return Some(format!("#{}", id));
+    }
+    match input.kind {
+        InputKind::Radio(value) => Some(format!("{}={}", input.name?, value)),
+        _ => input.name,
+    }
+}
+
+// Como `input_value`, pero un `radio` también guarda que está sin marcar
+fn state_value(widget: &Widget) -> Option<InputValue> {
+    match input_of(widget)?.kind {
+        InputKind::Radio(_) => Some(InputValue::Bool(widget.downcast_ref::<CheckButton>()?.is_active())),
+        _ => input_value(widget),
+    }
+}
+
+fn set_input_value(widget: &Widget, value: &InputValue) -> Option<()> {
+    match (input_of(widget)?.kind, value) {
+        (InputKind::Text, InputValue::Text(text)) => widget.dynamic_cast_ref::<Editable>()?.set_text(text),
+        (InputKind::Number, InputValue::Number(number)) => widget.downcast_ref::<SpinButton>()?.set_value(*number),
+        (InputKind::Checkbox | InputKind::Radio(_), InputValue::Bool(active)) => {
+            widget.downcast_ref::<CheckButton>()?.set_active(*active)
+        }
+        (InputKind::Range, InputValue::Number(number)) => widget.downcast_ref::<Scale>()?.set_value(*number),
+        (InputKind::Color, InputValue::Text(color)) => {
+            widget.downcast_ref::<ColorDialogButton>()?.set_rgba(&gdk::RGBA::parse(color).ok()?)
+        }
+        (InputKind::Date, InputValue::Text(date)) => {
+            let button = widget.downcast_ref::<MenuButton>()?;
+            let day = parse_date(date)?;
+            button.set_label(&format_date(&day));
+            if let Some(calendar) = button.popover()?.child().and_downcast::<Calendar>() {
+                calendar.select_day(&day);
+            }
+        }
+        (InputKind::TextArea, InputValue::Text(text)) => text_view(widget)?.buffer().set_text(text),
+        (InputKind::Select, InputValue::Text(selected)) => {
+            let drop_down = widget.downcast_ref::<DropDown>()?;
+            let model = drop_down.model()?;
+            let position = (0..model.n_items()).find(|&position| {
+                model
+                    .item(position)
+                    .and_downcast::<StringObject>()
+                    .is_some_and(|item| item.string() == selected.as_str())
+            })?;
+            drop_down.set_selected(position);
+        }
+        _ => {}
+    }
+    Some(())
+}
+
+// `disabled`, `name`, `required`, `pattern` y `onchange`, comunes a todos los controles
+fn finish_input(widget: Widget, node: &DomNode, kind: InputKind) -> Widget {
+    if flag(node, "disabled") {
+        widget.set_sensitive(false);
+    }
+
+    attach_input(&widget, node, kind);
+
+    if let Some(onchange) = event_handler(node, "onchange") {
+        connect_input_changed(&widget, move |widget| onchange.run(widget));
+    }
+
+    widget
+}
+
+fn attach_input(widget: &Widget, node: &DomNode, kind: InputKind) {
+    // Un patrón que no compila se avisa aquí y no se usa
+    let pattern = node.attributes.get("pattern").filter(|pattern| {
+        let valid = glib::Regex::new(pattern, glib::RegexCompileFlags::empty(), glib::RegexMatchFlags::empty()).is_ok();
+        if !valid {
+            eprintln!("  ⚠ pattern inválido en <{}>: \"{}\"", node.tag_name, pattern);
+        }
+        valid
+    });
+
+    let input = Input {
+        kind,
+        name: node.attributes.get("name").cloned(),
+        required: flag(node, "required"),
+        pattern: pattern.cloned(),
+    };
+    // SAFETY: la clave solo se usa con `Input`, ver `input_of`
+    unsafe { widget.set_data(INPUT_KEY, input) };
+}
+
+fn editable_input(editable: impl IsA<Editable> + IsA<Widget>, node: &DomNode) -> Widget {
+    if let Some(value) = node.attributes.get("value") {
+        editable.set_text(value);
+    }
+    if let Some(placeholder) = node.attributes.get("placeholder") {
+        editable.set_property("placeholder-text", placeholder);
+    }
+    editable.upcast()
+}
+
+// Sin límites, como en HTML; los decimales salen de `step`
+fn number_input(node: &DomNode) -> Widget {
+    let min = number_attribute(node, "min").unwrap_or(i32::MIN as f64);
+    let max = number_attribute(node, "max").unwrap_or(i32::MAX as f64).max(min);
+    let step = number_attribute(node, "step").filter(|step| *step > 0.0).unwrap_or(1.0);
+
+    let spin = SpinButton::with_range(min, max, step);
+    spin.set_digits(step_decimals(node));
+    spin.set_value(number_attribute(node, "value").unwrap_or(min.max(0.0).min(max)));
+    spin.upcast()
+}
+
+// Los `radio` con el mismo `name` forman un grupo en la ventana
+fn check_input(node: &DomNode, group: Option<&String>) -> Widget {
+    let check = CheckButton::new();
+    if let Some(label) = node.attributes.get("label") {
+        check.set_label(Some(label));
+    }
+    check.set_active(flag(node, "checked"));
+
+    if let Some(group) = group {
+        check.add_css_class("radio");
+        check.connect_realize({
+            let group = group.clone();
+            move |check| {
+                let Some(root) = check.root() else {
+                    return;
+                };
+                let first = find_descendant(root.upcast_ref(), &|other| {
+                    other != check.upcast_ref::<Widget>()
+                        && input_of(other).is_some_and(|input| {
+                            matches!(input.kind, InputKind::Radio(_)) && input.name.as_deref() == Some(group.as_str())
+                        })
+                });
+                if let Some(first) = first.and_then(|first| first.downcast::<CheckButton>().ok()) {
+                    check.set_group(Some(&first));
+                }
+            }
+        });
+    }
+
+    check.upcast()
+}
+
+// De 0 a 100 y a mitad de camino si no se indica, como en HTML
+fn range_input(node: &DomNode) -> Widget {
+    let min = number_attribute(node, "min").unwrap_or(0.0);
+    let max = number_attribute(node, "max").unwrap_or(100.0).max(min);
+    let step = number_attribute(node, "step").filter(|step| *step > 0.0).unwrap_or(1.0);
+
+    let scale = Scale::with_range(Orientation::Horizontal, min, max, step);
+    scale.set_digits(step_decimals(node) as i32);
+    scale.set_draw_value(true);
+    scale.set_width_request(130);
+    scale.set_value(number_attribute(node, "value").unwrap_or((min + max) / 2.0));
+    scale.upcast()
+}
+
+fn color_input(node: &DomNode) -> Widget {
+    let button = ColorDialogButton::new(Some(ColorDialog::new()));
+
+    let value = node.attributes.get("value").map(|value| value.trim()).unwrap_or("#000000");
+    match gdk::RGBA::parse(value) {
+        Ok(rgba) => button.set_rgba(&rgba),
+        Err(_) => eprintln!("  ⚠ Color inválido en <input type=\"color\">: \"{}\"", value),
+    }
+    button.upcast()
+}
+
+// Botón con la fecha (`AAAA-MM-DD`) que abre un calendario; `min` y `max`
+// limitan el día que se puede elegir
+fn date_input(node: &DomNode) -> Widget {
+    let date = |name| {
+        let value = node.attributes.get(name)?;
+        let date = parse_date(value);
+        if date.is_none() {
+            eprintln!("  ⚠ {} en <input type=\"date\"> debe ser AAAA-MM-DD, no \"{}\"", name, value);
+        }
+        date
+    };
+    let value = date("value");
+    let min = date("min");
+    let max = date("max");
+
+    let placeholder = node.attributes.get("placeholder").map(String::as_str).unwrap_or("AAAA-MM-DD");
+    let button = MenuButton::builder().label(placeholder).build();
+
+    let calendar = Calendar::new();
+    if let Some(value) = &value {
+        calendar.select_day(value);
+        button.set_label(&format_date(value));
+    }
+
+    calendar.connect_day_selected({
+        let button = button.clone();
+        move |calendar| {
+            let mut day = calendar.date();
+            if let Some(min) = min.as_ref().filter(|min| day < **min) {
+                day = min.clone();
+            }
+            if let Some(max) = max.as_ref().filter(|max| day > **max) {
+                day = max.clone();
+            }
+            if day != calendar.date() {
+                calendar.select_day(&day);
+                return;
+            }
+
+            button.set_label(&format_date(&day));
+            button.popdown();
+        }
+    });
+
+    button.set_popover(Some(&Popover::builder().child(&calendar).build()));
+    button.upcast()
+}
+
+fn parse_date(value: &str) -> Option<glib::DateTime> {
+    let mut parts = value.trim().splitn(3, '-').map(|part| part.parse::<i32>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()
+}
+
+fn format_date(date: &glib::DateTime) -> String {
+    format!("{:04}-{:02}-{:02}", date.year(), date.month(), date.day_of_month())
+}
+
+// Decimales con los que se escribe `step` (`0.25` → 2)
+fn step_decimals(node: &DomNode) -> u32 {
+    node.attributes
+        .get("step")
+        .and_then(|step| step.trim().split_once('.'))
+        .map_or(0, |(_, decimals)| decimals.len() as u32)
+}
+
+fn text_view(widget: &Widget) -> Option<TextView> {
+    widget.downcast_ref::<ScrolledWindow>()?.child()?.downcast().ok()
+}
+
+/// Primer descendiente de `widget` que cumple `predicate`, en orden de documento.
+pub fn find_descendant(widget: &Widget, predicate: &dyn Fn(&Widget) -> bool) -> Option<Widget> {
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if predicate(&current) {
+            return Some(current);
+        }
+        if let Some(found) = find_descendant(&current, predicate) {
+            return Some(found);
+        }
+        child = current.next_sibling();
+    }
+    None
+}
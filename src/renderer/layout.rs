@@ -0,0 +1,272 @@
+use gtk4::prelude::*;
+use gtk4::{Justification, Label, Overflow, PolicyType, ScrolledWindow, Widget};
+
+use super::attributes::invalid_attribute;
+use super::css::{parse_declarations, StyleSheet};
+use super::flex::invalid_flex_value;
+use super::grid::invalid_span;
+use crate::parser::diagnostics::Diagnostics;
+use crate::parser::html_parser::DomNode;
+
+/// Aplica al widget las propiedades de CSS web de su `style` que GTK no
+/// entiende (tamaño, `display`, `text-align`, `opacity` y `overflow`; las de
+/// los contenedores las lee `flex`). Con `overflow: auto | scroll` el widget queda
+/// dentro de un `ScrolledWindow`, que es lo que se devuelve. Los valores que
+/// no se pueden aplicar se ignoran; `check_layout` ya los avisó al cargar.
+pub fn apply_layout_style(widget: Widget, node: &DomNode) -> Widget {
+    let Some(style) = node.attributes.get("style") else {
+        return widget;
+    };
+
+    let mut width = None;
+    let mut height = None;
+    let mut hidden = false;
+    let mut overflow_x = None;
+    let mut overflow_y = None;
+
+    for declaration in parse_declarations(style) {
+        let value = declaration.value.as_str();
+
+        match declaration.name.as_str() {
+            "width" => width = parse_px(value),
+            "height" => height = parse_px(value),
+            "display" => hidden = value == "none",
+            "text-align" => set_text_align(&widget, value),
+            "opacity" => {
+                if let Ok(opacity) = value.parse::<f64>() {
+                    widget.set_opacity(opacity.clamp(0.0, 1.0));
+                }
+            }
+            "overflow" => {
+                let mut values = value.split_whitespace();
+                overflow_x = values.next().map(str::to_string);
+                overflow_y = values.next().map(str::to_string).or(overflow_x.clone());
+            }
+            "overflow-x" => overflow_x = Some(value.to_string()),
+            "overflow-y" => overflow_y = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    let widget = apply_overflow(widget, overflow_x.as_deref(), overflow_y.as_deref(), width, height);
+
+    if let Some(width) = width {
+        widget.set_width_request(width);
+    }
+    if let Some(height) = height {
+        widget.set_height_request(height);
+    }
+    if hidden {
+        widget.set_visible(false);
+    }
+
+    widget
+}
+
+// Solo píxeles: los porcentajes y demás unidades dependen de un tamaño que
+// GTK no conoce al crear el widget
+pub fn parse_px(value: &str) -> Option<i32> {
+    let number = value.strip_suffix("px").unwrap_or(value).trim();
+    number.parse::<f32>().ok().filter(|px| *px >= 0.0).map(|px| px.round() as i32)
+}
+
+/// Avisa de los valores de diseño que no se podrán aplicar, en los atributos,
+/// los `style` y los `<style>` de `node` y sus hijos. Se llama una vez al
+/// cargar el widget con su plantilla; los `{{ }}` y `var()` aún no tienen
+/// valor y no se comprueban.
+pub(crate) fn check_layout(node: &DomNode, diagnostics: &mut Diagnostics) {
+    let mut names: Vec<&String> = node.attributes.keys().collect();
+    names.sort();
+    for name in names {
+        let value = node.attributes[name].trim();
+        if value.contains("{{") {
+            continue;
+        }
+        if let Some(error) = invalid_layout_attribute(node, name, value) {
+            diagnostics.warning(node.span, format!("{} en <{}>: {}, se ignora", name, node.tag_name, error));
+        }
+    }
+    if node.tag_name == "table" {
+        for caption in node.children.iter().filter(|child| child.tag_name == "caption") {
+            diagnostics.warning(caption.span, "<caption> no está soportado en <table>, se ignora");
+        }
+    }
+
+    let mut check = |name: &str, value: &str| {
+        if let Some(message) = invalid_layout_value(name, value.trim()) {
+            diagnostics.warning(node.span, message);
+        }
+    };
+
+    if let Some(style) = node.attributes.get("style") {
+        for declaration in parse_declarations(style) {
+            check(&declaration.name, &declaration.value);
+        }
+    }
+    if node.tag_name == "style" {
+        let css: String = node.children.iter().filter_map(|child| child.text_content.as_deref()).collect();
+        for declaration in StyleSheet::parse(&css).declarations() {
+            check(&declaration.name, &declaration.value);
+        }
+    }
+
+    for child in &node.children {
+        check_layout(child, diagnostics);
+    }
+}
+
+// En los campos de formulario y las imágenes `align`, `wrap`... no son de
+// diseño flexible
+fn invalid_layout_attribute(node: &DomNode, name: &str, value: &str) -> Option<String> {
+    let container = !matches!(node.tag_name.as_str(), "input" | "checkbox" | "range" | "textarea" | "select" | "img");
+    match name {
+        "columns" if node.tag_name == "grid" => invalid_span(value),
+        "rowspan" | "colspan" => invalid_span(value),
+        "direction" | "align" | "justify" | "wrap" | "grow" | "shrink" if container => invalid_flex_value(name, value),
+        _ => invalid_attribute(node, name, value),
+    }
+}
+
+fn invalid_layout_value(name: &str, value: &str) -> Option<String> {
+    if value.contains("{{") || value.contains("var(") {
+        return None;
+    }
+
+    let valid = match name {
+        "width" | "height" | "row-gap" | "column-gap" => parse_px(value).is_some(),
+        "gap" => value.split_whitespace().all(|gap| parse_px(gap).is_some()),
+        "opacity" => value.parse::<f64>().is_ok(),
+        "text-align" => text_align(value).is_some(),
+        _ => match invalid_flex_value(name, value) {
+            Some(error) => return Some(format!("{}: {}, se ignora", name, error)),
+            None => true,
+        },
+    };
+    if valid {
+        return None;
+    }
+
+    Some(match name {
+        "opacity" | "text-align" => format!("valor no válido para {}: \"{}\"", name, value),
+        _ => format!("{} solo admite píxeles, se ignora \"{}\"", name, value),
+    })
+}
+
+fn text_align(value: &str) -> Option<(f32, Justification)> {
+    match value {
+        "left" | "start" => Some((0.0, Justification::Left)),
+        "center" => Some((0.5, Justification::Center)),
+        "right" | "end" => Some((1.0, Justification::Right)),
+        "justify" => Some((0.0, Justification::Fill)),
+        _ => None,
+    }
+}
+
+// Se alinean el propio `Label` o los textos sueltos del contenedor; los
+// elementos de dentro ya heredan `text-align` en su `style`
+fn set_text_align(widget: &Widget, value: &str) {
+    let Some((xalign, justify)) = text_align(value) else {
+        return;
+    };
+
+    let align = |label: &Label| {
+        label.set_xalign(xalign);
+        label.set_justify(justify);
+    };
+
+    if let Some(label) = widget.downcast_ref::<Label>() {
+        align(label);
+        return;
+    }
+
+    let mut child = widget.first_child();
+    while let Some(current) = child {
+        if let Some(label) = current.downcast_ref::<Label>() {
+            if label.css_name() == "label" {
+                align(label);
+            }
+        }
+        child = current.next_sibling();
+    }
+}
+
+fn apply_overflow(widget: Widget, x: Option<&str>, y: Option<&str>, width: Option<i32>, height: Option<i32>) -> Widget {
+    let scrolls = |value: Option<&str>| matches!(value, Some("auto" | "scroll"));
+
+    if !scrolls(x) && !scrolls(y) {
+        if x == Some("hidden") || y == Some("hidden") {
+            widget.set_overflow(Overflow::Hidden);
+        } else if x == Some("visible") || y == Some("visible") {
+            widget.set_overflow(Overflow::Visible);
+        }
+        return widget;
+    }
+
+    let policy = |value: Option<&str>| match value {
+        Some("auto") => PolicyType::Automatic,
+        Some("scroll") => PolicyType::Always,
+        Some("hidden") => PolicyType::External,
+        _ => PolicyType::Never,
+    };
+
+    // Sin tamaño fijo ocupa lo que su contenido y solo se desplaza si la
+    // ventana es más pequeña
+    let scrolled = ScrolledWindow::builder()
+        .hscrollbar_policy(policy(x))
+        .vscrollbar_policy(policy(y))
+        .propagate_natural_width(width.is_none())
+        .propagate_natural_height(height.is_none())
+        .child(&widget)
+        .build();
+
+    scrolled.upcast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::diagnostics::Span;
+
+    fn node(tag_name: &str, attributes: &[(&str, &str)], line: usize) -> DomNode {
+        DomNode {
+            tag_name: tag_name.to_string(),
+            attributes: attributes.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+            span: Some(Span { line, column: 5 }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn invalid_attributes_are_reported_at_load_with_their_line() {
+        let body = DomNode {
+            tag_name: "body".to_string(),
+            children: vec![
+                node("div", &[("direction", "row"), ("width", "50%"), ("margin", "4 8")], 2),
+                node("div", &[("halign", "middle"), ("style", "flex-wrap: yes")], 3),
+                node("td", &[("colspan", "0"), ("justify", "{{ justify }}")], 4),
+                node("input", &[("type", "range"), ("max", "mucho"), ("wrap", "soft")], 5),
+                node("range", &[("step", "poco")], 6),
+            ],
+            ..Default::default()
+        };
+
+        let mut diagnostics = Diagnostics::new("", None);
+        check_layout(&body, &mut diagnostics);
+        let warnings: Vec<(Option<usize>, String)> = diagnostics
+            .into_warnings()
+            .into_iter()
+            .map(|warning| (warning.span.map(|span| span.line), warning.message))
+            .collect();
+
+        assert_eq!(
+            warnings,
+            [
+                (Some(3), "halign en <div>: alineación desconocida \"middle\" (fill, start, center, end o baseline), se ignora".to_string()),
+                (Some(3), "flex-wrap: se esperaba wrap o nowrap, no \"yes\", se ignora".to_string()),
+                (Some(4), "colspan en <td>: debe ser un número mayor que 0, no \"0\", se ignora".to_string()),
+                (Some(5), "max en <input>: se esperaba un número, no \"mucho\", se ignora".to_string()),
+                (Some(6), "step en <range>: se esperaba un número, no \"poco\", se ignora".to_string()),
+            ]
+        );
+    }
+}
@@ -1 +1,16 @@
+pub mod access;
+pub mod actions;
+mod attributes;
+pub mod commands;
+mod css;
+mod flex;
+mod form;
+mod grid;
 pub mod gtk_renderer;
+pub mod inline;
+mod inline_flow;
+pub mod inputs;
+pub mod layout;
+mod overlay;
+mod sized;
+pub mod stylesheet;
@@ -0,0 +1,169 @@
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{gdk, Align, Orientation, Overlay, Widget};
+
+use super::attributes::{absolute_position, overlay_offset};
+use super::flex::FlexItem;
+use super::gtk_renderer::render_dom_to_gtk;
+use super::inline::{inline_label, inline_markup};
+use crate::parser::html_parser::DomNode;
+
+/// Hijo con `position="absolute"`, ya separado del resto.
+pub struct AbsoluteItem {
+    widget: Widget,
+    left: i32,
+    top: i32,
+}
+
+/// Separa los hijos con `position="absolute"`, que no ocupan sitio entre los demás.
+pub fn split_absolute(items: Vec<FlexItem>) -> (Vec<FlexItem>, Vec<AbsoluteItem>) {
+    let mut flow = Vec::new();
+    let mut absolute = Vec::new();
+
+    for item in items {
+        match item.node.and_then(absolute_position) {
+            Some((left, top)) => absolute.push(AbsoluteItem { widget: item.widget, left, top }),
+            None => flow.push(item),
+        }
+    }
+
+    (flow, absolute)
+}
+
+/// Pone los hijos absolutos encima de `content` con un `GtkOverlay` cuyo nodo
+/// CSS se llama `css_name`. No cuentan para el tamaño y pueden salirse.
+pub fn with_absolute_layer(css_name: &str, content: Widget, absolute: Vec<AbsoluteItem>) -> Widget {
+    let overlay = Overlay::builder().css_name(css_name).child(&content).build();
+    overlay.add_overlay(&absolute_layer(absolute));
+    overlay.upcast()
+}
+
+/// `<overlay>`: el primer hijo marca el tamaño y el resto se apilan encima,
+/// colocados con su `halign`/`valign` y movidos `offset-x`/`offset-y` píxeles.
+pub fn render_overlay(node: &DomNode) -> Widget {
+    let (items, absolute) = split_absolute(layers(node));
+    let overlay = Overlay::builder().css_name("overlay").build();
+
+    let mut items = items.into_iter();
+    if let Some(base) = items.next() {
+        overlay.set_child(Some(&base.widget));
+    }
+
+    let mut offsets = Vec::new();
+    for item in items {
+        let (x, y) = item.node.map(overlay_offset).unwrap_or_default();
+        if (x, y) != (0, 0) {
+            offsets.push((item.widget.clone(), x, y));
+        }
+        overlay.add_overlay(&item.widget);
+    }
+
+    if !absolute.is_empty() {
+        overlay.add_overlay(&absolute_layer(absolute));
+    }
+
+    // Sin desplazamiento GTK coloca el hijo como siempre
+    if !offsets.is_empty() {
+        overlay.connect_get_child_position(move |overlay, widget| {
+            let (_, x, y) = offsets.iter().find(|(child, ..)| child == widget)?;
+            let mut position = child_position(overlay, widget);
+            position.set_x(position.x() + x);
+            position.set_y(position.y() + y);
+            Some(position)
+        });
+    }
+
+    overlay.upcast()
+}
+
+// Cada hijo es una capa, también los elementos en línea
+fn layers(node: &DomNode) -> Vec<FlexItem<'_>> {
+    node.children
+        .iter()
+        .filter(|child| child.tag_name != "style" && child.tag_name != "link")
+        .filter_map(|child| match child.tag_name.as_str() {
+            "text" if inline_markup(&[child]).is_empty() => None,
+            "text" => Some(FlexItem {
+                widget: inline_label(&[child]).upcast(),
+                node: None,
+            }),
+            _ => Some(FlexItem {
+                widget: render_dom_to_gtk(child),
+                node: Some(child),
+            }),
+        })
+        .collect()
+}
+
+fn absolute_layer(absolute: Vec<AbsoluteItem>) -> AbsoluteLayer {
+    let layer: AbsoluteLayer = glib::Object::new();
+    for item in absolute {
+        layer.put(&item.widget, item.left as f64, item.top as f64);
+    }
+    layer
+}
+
+// Lo mismo que hace GtkOverlay: tamaño natural (o todo con `fill`) y
+// alineado dentro del overlay
+fn child_position(overlay: &Overlay, widget: &Widget) -> gdk::Rectangle {
+    let (width, height) = (overlay.width(), overlay.height());
+
+    let (min_width, natural_width, ..) = widget.measure(Orientation::Horizontal, -1);
+    let child_width = match widget.halign() {
+        Align::Fill => width,
+        _ => natural_width.min(width).max(min_width),
+    };
+    let (min_height, natural_height, ..) = widget.measure(Orientation::Vertical, child_width);
+    let child_height = match widget.valign() {
+        Align::Fill => height,
+        _ => natural_height.min(height).max(min_height),
+    };
+
+    let offset = |align: Align, available: i32, size: i32| match align {
+        Align::Center => (available - size) / 2,
+        Align::End => available - size,
+        _ => 0,
+    };
+
+    gdk::Rectangle::new(
+        offset(widget.halign(), width, child_width),
+        offset(widget.valign(), height, child_height),
+        child_width,
+        child_height,
+    )
+}
+
+mod imp {
+    use super::*;
+    use gtk4::glib;
+
+    #[derive(Default)]
+    pub struct AbsoluteLayer;
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for AbsoluteLayer {
+        const NAME: &'static str = "YtmlAbsoluteLayer";
+        type Type = super::AbsoluteLayer;
+        type ParentType = gtk4::Fixed;
+    }
+
+    impl ObjectImpl for AbsoluteLayer {}
+
+    impl WidgetImpl for AbsoluteLayer {
+        // La capa cubre todo el contenedor; así los clics que no caen en un
+        // hijo absoluto llegan a lo que hay debajo
+        fn contains(&self, _x: f64, _y: f64) -> bool {
+            false
+        }
+    }
+
+    impl FixedImpl for AbsoluteLayer {}
+}
+
+glib::wrapper! {
+    /// `GtkFixed` donde van los hijos con `position="absolute"`.
+    pub struct AbsoluteLayer(ObjectSubclass<imp::AbsoluteLayer>)
+        @extends gtk4::Fixed, Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
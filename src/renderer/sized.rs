@@ -0,0 +1,209 @@
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+use gtk4::{Align, Orientation, SizeRequestMode, Widget};
+use std::cell::Cell;
+
+use crate::parser::geometry::Length;
+
+/// Tamaño pedido en un eje. Los porcentajes son del tamaño del padre.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AxisSize {
+    pub size: Option<Length>,
+    pub min: Option<Length>,
+    pub max: Option<Length>,
+}
+
+impl AxisSize {
+    /// Solo hace falta un `SizedBin` para los porcentajes y el máximo; el
+    /// resto se resuelve con `set_size_request`.
+    pub fn needs_bin(&self) -> bool {
+        let is_percent = |length: Option<Length>| matches!(length, Some(Length::Percent(_)));
+        is_percent(self.size) || is_percent(self.min) || self.max.is_some()
+    }
+
+    // Sin padre todavía no se pueden calcular los porcentajes
+    fn resolve(length: Option<Length>, available: Option<i32>) -> Option<i32> {
+        match length? {
+            px @ Length::Px(_) => Some(px.resolve(0)),
+            percent => available.map(|available| percent.resolve(available)),
+        }
+    }
+
+    // Mínimo y natural del contenedor a partir de los del hijo
+    fn measure(&self, child_min: i32, child_nat: i32, available: Option<i32>) -> (i32, i32) {
+        let mut min = child_min;
+        let mut nat = Self::resolve(self.size, available).unwrap_or(child_nat);
+
+        if let Some(max) = Self::resolve(self.max, available) {
+            nat = nat.min(max);
+        }
+        if let Some(min_size) = Self::resolve(self.min, available) {
+            min = min.max(min_size);
+        }
+
+        (min, nat.max(min))
+    }
+
+    // Tamaño del hijo dentro de `allocated`: el de `width` si cabe, si no
+    // todo, y siempre entre los límites y sin bajar de su mínimo
+    fn child_size(&self, allocated: i32, child_min: i32, available: Option<i32>) -> i32 {
+        let mut size = Self::resolve(self.size, available).map_or(allocated, |size| size.min(allocated));
+        if let Some(max) = Self::resolve(self.max, available) {
+            size = size.min(max);
+        }
+        if let Some(min) = Self::resolve(self.min, available) {
+            size = size.max(min);
+        }
+        size.max(child_min)
+    }
+}
+
+mod imp {
+    use super::*;
+    use gtk4::glib;
+
+    #[derive(Default)]
+    pub struct SizedBin {
+        pub width: Cell<AxisSize>,
+        pub height: Cell<AxisSize>,
+        /// Tamaño del padre (ancho, alto) con el que se midió por última vez.
+        pub measured_in: Cell<(Option<i32>, Option<i32>)>,
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for SizedBin {
+        const NAME: &'static str = "YtmlSizedBin";
+        type Type = super::SizedBin;
+        type ParentType = Widget;
+    }
+
+    impl ObjectImpl for SizedBin {
+        fn dispose(&self) {
+            while let Some(child) = self.obj().first_child() {
+                child.unparent();
+            }
+        }
+    }
+
+    impl WidgetImpl for SizedBin {
+        fn request_mode(&self) -> SizeRequestMode {
+            self.obj()
+                .first_child()
+                .map(|child| child.request_mode())
+                .unwrap_or(SizeRequestMode::ConstantSize)
+        }
+
+        fn measure(&self, orientation: Orientation, for_size: i32) -> (i32, i32, i32, i32) {
+            let Some(child) = self.obj().first_child() else {
+                return (0, 0, -1, -1);
+            };
+            self.measured_in.set(self.parent_size());
+
+            // `for_size` es el tamaño del contenedor en el otro eje; el hijo
+            // puede recibir menos
+            let other = match orientation {
+                Orientation::Horizontal => Orientation::Vertical,
+                _ => Orientation::Horizontal,
+            };
+            let for_size = if for_size < 0 {
+                for_size
+            } else {
+                let (other_min, ..) = child.measure(other, -1);
+                self.axis(other).child_size(for_size, other_min, self.available(other))
+            };
+
+            let (child_min, child_nat, ..) = child.measure(orientation, for_size);
+            let (min, nat) = self.axis(orientation).measure(child_min, child_nat, self.available(orientation));
+            (min, nat, -1, -1)
+        }
+
+        fn size_allocate(&self, width: i32, height: i32, _baseline: i32) {
+            let Some(child) = self.obj().first_child() else {
+                return;
+            };
+
+            // El padre mide a sus hijos antes de saber su propio tamaño, así
+            // que los porcentajes se midieron con el anterior: al cambiar se
+            // vuelve a medir, fuera de la asignación en curso
+            let parent_size = self.parent_size();
+            if parent_size != self.measured_in.get() {
+                self.measured_in.set(parent_size);
+                let bin = self.obj().downgrade();
+                glib::idle_add_local_once(move || {
+                    if let Some(bin) = bin.upgrade() {
+                        bin.queue_resize();
+                    }
+                });
+            }
+
+            let (min_width, ..) = child.measure(Orientation::Horizontal, -1);
+            let child_width = self.axis(Orientation::Horizontal).child_size(
+                width,
+                min_width,
+                self.available(Orientation::Horizontal),
+            );
+            let (min_height, ..) = child.measure(Orientation::Vertical, child_width);
+            let child_height = self.axis(Orientation::Vertical).child_size(
+                height,
+                min_height,
+                self.available(Orientation::Vertical),
+            );
+
+            let x = offset(child.halign(), width, child_width);
+            let y = offset(child.valign(), height, child_height);
+            child.size_allocate(&gtk4::Allocation::new(x, y, child_width, child_height), -1);
+        }
+    }
+
+    impl SizedBin {
+        fn axis(&self, orientation: Orientation) -> AxisSize {
+            match orientation {
+                Orientation::Horizontal => self.width.get(),
+                _ => self.height.get(),
+            }
+        }
+
+        // Tamaño actual del padre, base de los porcentajes
+        fn available(&self, orientation: Orientation) -> Option<i32> {
+            let parent = self.obj().parent()?;
+            let size = match orientation {
+                Orientation::Horizontal => parent.width(),
+                _ => parent.height(),
+            };
+            (size > 0).then_some(size)
+        }
+
+        fn parent_size(&self) -> (Option<i32>, Option<i32>) {
+            (self.available(Orientation::Horizontal), self.available(Orientation::Vertical))
+        }
+    }
+
+    // El hijo se coloca según su propio `halign`/`valign` dentro del espacio recibido
+    fn offset(align: Align, allocated: i32, size: i32) -> i32 {
+        match align {
+            Align::Center => (allocated - size) / 2,
+            Align::End => allocated - size,
+            _ => 0,
+        }
+        .max(0)
+    }
+}
+
+glib::wrapper! {
+    /// Contenedor de un solo hijo que le da `width`/`height` en porcentaje del
+    /// padre y `max-width`, que GTK no tiene.
+    pub struct SizedBin(ObjectSubclass<imp::SizedBin>)
+        @extends Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
+
+impl SizedBin {
+    pub fn new(child: &Widget, width: AxisSize, height: AxisSize) -> Self {
+        let bin: SizedBin = glib::Object::new();
+        bin.imp().width.set(width);
+        bin.imp().height.set(height);
+        child.set_parent(&bin);
+        bin
+    }
+}
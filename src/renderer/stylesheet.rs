@@ -0,0 +1,437 @@
+use gtk4::prelude::*;
+use gtk4::{glib, ApplicationWindow, CssProvider};
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Write;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use super::access::Access;
+use super::attributes::{padding_class, parse_padding};
+use super::css::{compute_styles, format_declarations, is_layout_property, parse_declarations, StyleSheet, Variables};
+use crate::parser::html_parser::DomNode;
+use crate::parser::permissions::{Denied, Permissions};
+
+/// Los estilos de cada widget van por encima del `style.css` global.
+const WIDGET_STYLE_PRIORITY: u32 = gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION + 1;
+
+/// Y los atributos `style` por encima de cualquier hoja de estilos, como en HTML.
+const INLINE_STYLE_PRIORITY: u32 = gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION + 2;
+
+/// `@import` anidados como mucho en los estilos de un widget; también corta
+/// los ciclos.
+const MAX_IMPORT_DEPTH: usize = 8;
+
+static NEXT_SCOPE: AtomicUsize = AtomicUsize::new(0);
+
+/// `style.css` ya procesado: sus variables y propiedades de diseño también
+/// valen para los widgets.
+static GLOBAL_STYLESHEET: OnceLock<StyleSheet> = OnceLock::new();
+
+/// Estilos por defecto de las etiquetas (tamaño de los encabezados, `<pre>`,
+/// `<hr>`), por debajo de `style.css` para que se puedan sobrescribir.
+pub fn load_default_stylesheet() {
+    let Some(display) = gtk4::gdk::Display::default() else {
+        return;
+    };
+
+    let provider = CssProvider::new();
+    report_parsing_errors(&provider);
+    provider.load_from_string(include_str!("default.css"));
+    gtk4::style_context_add_provider_for_display(
+        &display,
+        &provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION - 1,
+    );
+}
+
+/// Carga la hoja de estilos global del proyecto para todas las ventanas.
+/// Sus `url()` se resuelven relativas al propio archivo.
+pub fn load_global_stylesheet(path: &Path) {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(_) => {
+            crate::vprintln!("Sin hoja de estilos global ({:?})", path);
+            return;
+        }
+    };
+
+    let Some(display) = gtk4::gdk::Display::default() else {
+        eprintln!("✗ No hay display para cargar {:?}", path);
+        return;
+    };
+
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let sheet = GLOBAL_STYLESHEET.get_or_init(|| StyleSheet::parse(&rewrite_urls(&content, base_dir, None)));
+    let mut variables = Variables::default();
+    sheet.collect_variables(&mut variables);
+
+    let provider = CssProvider::new();
+    report_parsing_errors(&provider);
+    provider.load_from_string(&sheet.to_gtk_css(None, &variables));
+    gtk4::style_context_add_provider_for_display(
+        &display,
+        &provider,
+        gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION,
+    );
+}
+
+/// Estilos propios de una ventana de widget: sus `<style>`,
+/// `<link rel="stylesheet">` y atributos `style`, con las reglas limitadas a
+/// esa ventana.
+#[derive(Clone)]
+pub struct ScopedStyle {
+    provider: CssProvider,
+    inline_provider: CssProvider,
+    /// Clase CSS única que se añade a la ventana.
+    scope: String,
+    /// Carpeta del archivo del widget, base de los `href` y `url()` relativos.
+    base_dir: PathBuf,
+    /// Los `<link>`, `@import` y `url()` fuera de la carpeta del widget
+    /// necesitan permiso de lectura.
+    access: Access,
+}
+
+impl ScopedStyle {
+    pub fn attach(window: &ApplicationWindow, access: &Access) -> Self {
+        let scope = format!("ytml-widget-{}", NEXT_SCOPE.fetch_add(1, Ordering::Relaxed));
+        window.add_css_class(&scope);
+
+        let provider = add_window_provider(window, WIDGET_STYLE_PRIORITY);
+        let inline_provider = add_window_provider(window, INLINE_STYLE_PRIORITY);
+
+        ScopedStyle {
+            provider,
+            inline_provider,
+            scope,
+            base_dir: access.dir().unwrap_or_default(),
+            access: access.clone(),
+        }
+    }
+
+    /// (Re)carga las hojas de estilo y los atributos `style` del body.
+    ///
+    /// Devuelve el body que hay que renderizar: el `style` de cada elemento
+    /// lleva además las propiedades de diseño (`width`, `gap`, `display`...)
+    /// de las reglas que lo seleccionan, que GTK no entiende y aplica el renderer.
+    pub fn load(&self, body: &DomNode) -> DomNode {
+        let mut css = String::new();
+        collect_css(body, &self.base_dir, &self.access, &mut css);
+        let sheet = StyleSheet::parse(&css);
+
+        let mut variables = Variables::default();
+        let mut layout_rules = Vec::new();
+        if let Some(global) = GLOBAL_STYLESHEET.get() {
+            global.collect_variables(&mut variables);
+            layout_rules.extend(global.layout_rules());
+        }
+        sheet.collect_variables(&mut variables);
+        layout_rules.extend(sheet.layout_rules());
+
+        self.provider.load_from_string(&sheet.to_gtk_css(Some(&self.scope), &variables));
+
+        let body = compute_styles(body, &layout_rules, &variables);
+
+        let mut inline_css = String::new();
+        collect_padding(&body, &self.scope, &mut inline_css);
+        collect_inline_styles(&body, &self.scope, &mut inline_css);
+        self.inline_provider.load_from_string(&inline_css);
+
+        body
+    }
+}
+
+/// Clase CSS del atributo `style` de un elemento. Depende solo de las
+/// declaraciones, así que el renderer y `ScopedStyle` la calculan por separado.
+pub fn inline_style_class(style: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    style.trim().hash(&mut hasher);
+    format!("ytml-style-{:x}", hasher.finish())
+}
+
+// El proveedor es del display, así que hay que quitarlo al cerrar la ventana
+fn add_window_provider(window: &ApplicationWindow, priority: u32) -> CssProvider {
+    let provider = CssProvider::new();
+    report_parsing_errors(&provider);
+    gtk4::style_context_add_provider_for_display(&WidgetExt::display(window), &provider, priority);
+
+    window.connect_destroy({
+        let provider = provider.clone();
+        move |window| {
+            gtk4::style_context_remove_provider_for_display(&WidgetExt::display(window), &provider);
+        }
+    });
+
+    provider
+}
+
+fn report_parsing_errors(provider: &CssProvider) {
+    provider.connect_parsing_error(|_, section, error| {
+        eprintln!("  ⚠ CSS {}: {}", section, error);
+    });
+}
+
+fn collect_css(node: &DomNode, base_dir: &Path, access: &Access, css: &mut String) {
+    match node.tag_name.as_str() {
+        "style" => {
+            let text: String = node
+                .children
+                .iter()
+                .filter_map(|child| child.text_content.as_deref())
+                .collect();
+            css.push_str(&widget_css(&text, base_dir, access, 0));
+            css.push('\n');
+        }
+        "link" => {
+            let is_stylesheet = node
+                .attributes
+                .get("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| r.eq_ignore_ascii_case("stylesheet")));
+            let Some(href) = node.attributes.get("href").filter(|_| is_stylesheet) else {
+                return;
+            };
+
+            let path = base_dir.join(href);
+            if !access.check(|permissions, base| permissions.check_read(&path, base)) {
+                return;
+            }
+            match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    let link_dir = path.parent().unwrap_or(base_dir);
+                    css.push_str(&widget_css(&content, link_dir, access, 0));
+                    css.push('\n');
+                }
+                Err(e) => eprintln!("  ✗ No se pudo leer la hoja de estilos {:?}: {}", path, e),
+            }
+        }
+        _ => {
+            for child in &node.children {
+                collect_css(child, base_dir, access, css);
+            }
+        }
+    }
+}
+
+// Van antes que los `style` para que `style="padding: ..."` gane al atributo.
+// Los valores inválidos ya los avisa el renderer
+fn collect_padding(node: &DomNode, scope: &str, css: &mut String) {
+    if let Some(padding) = node.attributes.get("padding").and_then(|value| parse_padding(value.trim()).ok()) {
+        let _ = writeln!(
+            css,
+            "window.{} .{} {{ padding: {}px {}px {}px {}px; }}",
+            scope,
+            padding_class(&padding),
+            padding.top,
+            padding.right,
+            padding.bottom,
+            padding.left
+        );
+    }
+
+    for child in &node.children {
+        collect_padding(child, scope, css);
+    }
+}
+
+// Reglas en orden de documento: con la misma prioridad gana la del elemento
+// más interno, que se escribe después
+fn collect_inline_styles(node: &DomNode, scope: &str, css: &mut String) {
+    if let Some(style) = node.attributes.get("style").filter(|style| !style.trim().is_empty()) {
+        let selector = format!("window.{} .{}", scope, inline_style_class(style));
+
+        // Las propiedades de diseño las aplica el renderer
+        let declarations: Vec<_> = parse_declarations(style)
+            .into_iter()
+            .filter(|declaration| !is_layout_property(&declaration.name))
+            .collect();
+        if !declarations.is_empty() {
+            let _ = writeln!(css, "{} {{ {}; }}", selector, format_declarations(&declarations));
+        }
+    }
+
+    for child in &node.children {
+        collect_inline_styles(child, scope, css);
+    }
+}
+
+// CSS de un widget con los `@import` que puede leer ya dentro y sus `url()`
+// comprobadas. Los `@import` que quedaran los cargaría GTK sin pasar por
+// los permisos
+fn widget_css(css: &str, base_dir: &Path, access: &Access, depth: usize) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = rest.find("@import") {
+        let end = rest[start..].find(';').map_or(rest.len(), |end| start + end + 1);
+        output.push_str(&rest[..start]);
+        let statement = rest[start + "@import".len()..end].trim_end_matches(';').trim();
+        if let Some(imported) = import(statement, base_dir, access, depth) {
+            output.push_str(&imported);
+            output.push('\n');
+        }
+        rest = &rest[end..];
+    }
+
+    output.push_str(rest);
+    rewrite_urls(&output, base_dir, Some(access))
+}
+
+// `@import url("a.css") screen` o `@import "a.css"`: el archivo, dentro de
+// un `@media` si lleva condiciones. Si no se puede leer no queda nada
+fn import(statement: &str, base_dir: &Path, access: &Access, depth: usize) -> Option<String> {
+    let (target, media) = match statement.strip_prefix("url(") {
+        Some(rest) => rest.split_once(')')?,
+        None => {
+            let quote = statement.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            statement[1..].split_once(quote)?
+        }
+    };
+    let target = target.trim().trim_matches(|c| c == '"' || c == '\'');
+
+    if url_scheme(target).is_some() {
+        eprintln!("  ⚠ @import \"{}\": solo se importan archivos del widget, se ignora", target);
+        return None;
+    }
+    let path = base_dir.join(target);
+    if depth >= MAX_IMPORT_DEPTH {
+        eprintln!("  ⚠ @import {:?}: más de {} @import anidados, se ignora", path, MAX_IMPORT_DEPTH);
+        return None;
+    }
+    if !access.check(|permissions, file| permissions.check_read(&path, file)) {
+        return None;
+    }
+
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("  ✗ No se pudo leer la hoja de estilos {:?}: {}", path, e);
+            return None;
+        }
+    };
+    let css = widget_css(&content, path.parent().unwrap_or(base_dir), access, depth + 1);
+    match media.trim() {
+        "" => Some(css),
+        media => Some(format!("@media {} {{\n{}\n}}", media, css)),
+    }
+}
+
+/// Adónde apunta una `url()` del CSS.
+enum CssUrl {
+    /// `data:`, `resource:` o vacía: no sale de la aplicación.
+    Inline,
+    File(PathBuf),
+    /// Otro esquema (`https:`...), que GTK podría descargar.
+    Remote,
+}
+
+/// Convierte las `url()` relativas en rutas `file://` absolutas, ya que GTK no
+/// sabe de qué archivo viene el CSS cargado desde texto. Con `access` (los
+/// estilos de un widget) las que el widget no puede leer pasan a ser `none`.
+pub fn rewrite_urls(css: &str, base_dir: &Path, access: Option<&Access>) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = rest.find("url(") {
+        let args_start = start + 4;
+        let Some(close) = rest[args_start..].find(')') else {
+            break;
+        };
+
+        let raw = &rest[args_start..args_start + close];
+        let target = raw.trim().trim_matches(|c| c == '"' || c == '\'');
+
+        output.push_str(&rest[..start]);
+        let replacement = match resolve_url(target, base_dir) {
+            CssUrl::Inline => None,
+            CssUrl::File(path) if allowed(access, |permissions, file| permissions.check_read(&path, file)) => {
+                glib::filename_to_uri(&path, None).ok().map(|uri| format!("url(\"{}\")", uri))
+            }
+            CssUrl::Remote if allowed(access, |permissions, file| permissions.check_uri(target, file)) => None,
+            CssUrl::File(_) | CssUrl::Remote => Some("none".to_string()),
+        };
+        match replacement {
+            Some(replacement) => output.push_str(&replacement),
+            None => output.push_str(&rest[start..args_start + close + 1]),
+        }
+        rest = &rest[args_start + close + 1..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+// Sin `access` (el `style.css` del proyecto) todo está permitido
+fn allowed(access: Option<&Access>, check: impl FnOnce(&Permissions, Option<&Path>) -> Result<(), Denied>) -> bool {
+    access.is_none_or(|access| access.check(check))
+}
+
+fn resolve_url(target: &str, base_dir: &Path) -> CssUrl {
+    match url_scheme(target).map(|scheme| scheme.to_ascii_lowercase()).as_deref() {
+        _ if target.is_empty() => CssUrl::Inline,
+        Some("data" | "resource") => CssUrl::Inline,
+        Some("file") => match glib::filename_from_uri(target) {
+            Ok((path, _)) => CssUrl::File(path),
+            Err(_) => CssUrl::Remote,
+        },
+        Some(_) => CssUrl::Remote,
+        None => {
+            let path = base_dir.join(target);
+            match path.is_absolute() {
+                true => CssUrl::File(path),
+                false => CssUrl::File(std::env::current_dir().unwrap_or_default().join(path)),
+            }
+        }
+    }
+}
+
+// `https:`, `data:`... pero no la unidad de una ruta de Windows (`C:`)
+fn url_scheme(target: &str) -> Option<&str> {
+    target
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDGETS: &str = "/nonexistent-ytml/widgets";
+
+    fn access() -> Access {
+        Access {
+            widget: "clock".to_string(),
+            file: Some(Path::new(WIDGETS).join("clock.ytml")),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn widget_urls_need_read_permission() {
+        let css = rewrite_urls(
+            "a { background: url(clock/bg.png); } b { background: url(\"weather/bg.png\"); } \
+             c { background: url(https://example.com/bg.png); } d { background: url(data:image/png;base64,AA); }",
+            Path::new(WIDGETS),
+            Some(&access()),
+        );
+
+        assert_eq!(
+            css,
+            "a { background: url(\"file:///nonexistent-ytml/widgets/clock/bg.png\"); } b { background: none; } \
+             c { background: none; } d { background: url(data:image/png;base64,AA); }"
+        );
+    }
+
+    #[test]
+    fn imports_outside_the_widget_folder_are_dropped() {
+        let css = widget_css(
+            "@import url(\"../secret.css\"); @import \"https://example.com/a.css\"; p { color: red; }",
+            Path::new(WIDGETS),
+            &access(),
+            0,
+        );
+
+        assert_eq!(css.trim(), "p { color: red; }");
+    }
+}
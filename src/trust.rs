@@ -0,0 +1,100 @@
+use gtk4::prelude::*;
+use gtk4::{gio, glib, AlertDialog, Application};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::parser::html_parser::WidgetDefinition;
+use crate::parser::permissions::Permissions;
+
+/// Permisos que el usuario ya aprobó, por widget: `archivo#id` y la suma
+/// SHA-256 de su `<permissions>`. Si el manifiesto cambia, hay que volver a
+/// preguntar.
+pub fn trust_path() -> PathBuf {
+    glib::user_config_dir().join("ytml").join("trusted.json")
+}
+
+/// Si `widget` no pide nada o ya se aprobó exactamente lo que pide.
+pub fn is_trusted(widget: &WidgetDefinition) -> bool {
+    widget.permissions.is_empty() || load().get(&key(widget)) == Some(&checksum(&widget.permissions))
+}
+
+/// Recuerda que el usuario aprobó los permisos actuales de `widget`.
+pub fn trust(widget: &WidgetDefinition) {
+    let mut trusted = load();
+    trusted.insert(key(widget), checksum(&widget.permissions));
+
+    let path = trust_path();
+    let written = serde_json::to_string_pretty(&trusted)
+        .map_err(std::io::Error::other)
+        .and_then(|text| {
+            path.parent().map_or(Ok(()), std::fs::create_dir_all)?;
+            std::fs::write(&path, text)
+        });
+
+    if let Err(error) = written {
+        eprintln!("  ⚠ No se pudo guardar la aprobación en {:?}: {}", path, error);
+    }
+}
+
+/// Pregunta al usuario si concede a `widget` los permisos que declara y
+/// llama a `on_answer` con la respuesta. La aplicación sigue abierta hasta
+/// que contesta, aunque aún no haya ninguna ventana.
+pub fn ask_trust(app: &Application, widget: &WidgetDefinition, on_answer: impl FnOnce(bool) + 'static) {
+    let file = widget
+        .data
+        .file
+        .as_deref()
+        .map(|file| format!(" ({})", file.display()))
+        .unwrap_or_default();
+    let mut detail = widget
+        .permissions
+        .describe()
+        .iter()
+        .map(|line| format!("• {}", line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !widget.permissions.exec.is_empty() {
+        detail.push_str("\n\nSolo se revisa lo que el widget les pasa a los programas; lo que hagan por su cuenta no se limita.");
+    }
+
+    let dialog = AlertDialog::builder()
+        .modal(true)
+        .message(format!("El widget '{}'{} pide permiso para:", widget.id, file))
+        .detail(detail)
+        .buttons(["No permitir", "Permitir"])
+        .cancel_button(0)
+        .default_button(1)
+        .build();
+
+    let hold = app.hold();
+    dialog.choose(None::<&gtk4::Window>, None::<&gio::Cancellable>, move |answer| {
+        drop(hold);
+        on_answer(matches!(answer, Ok(1)));
+    });
+}
+
+fn load() -> BTreeMap<String, String> {
+    std::fs::read_to_string(trust_path())
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+// El archivo con su ruta completa: dos widgets con el mismo id en carpetas
+// distintas son widgets distintos
+fn key(widget: &WidgetDefinition) -> String {
+    let file = widget
+        .data
+        .file
+        .as_deref()
+        .map(|file| file.canonicalize().unwrap_or_else(|_| file.to_path_buf()).display().to_string())
+        .unwrap_or_default();
+    format!("{}#{}", file, widget.id)
+}
+
+fn checksum(permissions: &Permissions) -> String {
+    let manifest = serde_json::to_string(permissions).unwrap_or_default();
+    glib::compute_checksum_for_data(glib::ChecksumType::Sha256, manifest.as_bytes())
+        .map(|sum| sum.to_string())
+        .unwrap_or_default()
+}